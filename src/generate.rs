@@ -62,61 +62,98 @@ pub struct Options {
     #[clap(short, long, value_parser, default_value_t = 100)]
     value_upper_bound: usize,
 
+    /// Generate a multiple-choice problem, with items spread round robin over this many classes.
+    /// The capacity ratio then sits between the lightest and heaviest choice of each class
+    #[clap(long, value_parser)]
+    class_count: Option<usize>,
+
     /// Where to write the problem file
     #[clap(short, long, value_parser)]
     output_path: PathBuf,
 }
 
 pub fn run(options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(class_count) = options.class_count {
+        if class_count == 0 || class_count > options.item_count {
+            let message = format!(
+                "Class count must be between 1 and the item count {}, got {}",
+                options.item_count, class_count
+            );
+            return Err(message.into());
+        }
+    }
+
     let output_file = File::create(&options.output_path)?;
     let mut output_writer = BufWriter::new(output_file);
     let mut rng = rand::thread_rng();
 
     writeln!(output_writer, "{}", options.item_count)?;
-    let weight_sum = match options.correlation {
+    let weights = match options.correlation {
         Correlation::None => write_no_correlation(options, &mut output_writer, &mut rng)?,
         Correlation::Some => write_some_correlation(options, &mut output_writer, &mut rng)?,
         Correlation::Strong => write_strong_correlation(options, &mut output_writer, &mut rng)?,
     };
 
+    let weight_sum: usize = weights.iter().sum();
     let capacity = if let Some(c) = options.capacity {
         c
+    } else if let Some(class_count) = options.class_count {
+        let (lightest, heaviest) = class_weight_sums(&weights, class_count);
+        lightest + (options.capacity_ratio * (heaviest - lightest) as f32).ceil() as usize
     } else {
         (options.capacity_ratio * weight_sum as f32).ceil() as usize
     };
     println!("Weight Sum: {}, Capacity: {}", weight_sum, capacity);
     writeln!(output_writer, "{}", capacity)?;
 
+    if let Some(class_count) = options.class_count {
+        for id in 0..options.item_count {
+            writeln!(output_writer, "class {} {}", id, id % class_count)?;
+        }
+    }
+
     Ok(())
 }
 
+/// Sums of the lightest, and of the heaviest, item weight of each class
+fn class_weight_sums(weights: &[usize], class_count: usize) -> (usize, usize) {
+    let mut lightest = vec![usize::MAX; class_count];
+    let mut heaviest = vec![0; class_count];
+    for (id, &weight) in weights.iter().enumerate() {
+        let class = id % class_count;
+        lightest[class] = lightest[class].min(weight);
+        heaviest[class] = heaviest[class].max(weight);
+    }
+    (lightest.iter().sum(), heaviest.iter().sum())
+}
+
 fn write_no_correlation<O: std::io::Write>(
     options: &Options,
     output: &mut O,
     rng: &mut ThreadRng,
-) -> Result<usize, Box<dyn std::error::Error>> {
+) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
     let value_distribution = Uniform::from(options.value_lower_bound..options.value_upper_bound);
     let weight_distribution = Uniform::from(options.weight_lower_bound..options.weight_upper_bound);
-    let mut weight_sum = 0;
+    let mut weights = Vec::with_capacity(options.item_count);
     for id in 0..options.item_count {
         let value = value_distribution.sample(rng);
         let weight = weight_distribution.sample(rng);
-        weight_sum += weight;
+        weights.push(weight);
         writeln!(output, "{} {} {}", id, value, weight)?;
     }
-    Ok(weight_sum)
+    Ok(weights)
 }
 
 fn write_some_correlation<O: std::io::Write>(
     options: &Options,
     output: &mut O,
     rng: &mut ThreadRng,
-) -> Result<usize, Box<dyn std::error::Error>> {
+) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
     let t_distribution = Uniform::from(0.0..1f32);
     let offset_distribution = Uniform::from(-1.0..1.0f32);
     let value_upper_bound_f32 = options.value_upper_bound as f32;
     let weight_upper_bound_f32 = options.weight_upper_bound as f32;
-    let mut weight_sum = 0;
+    let mut weights = Vec::with_capacity(options.item_count);
     for id in 0..options.item_count {
         let value_t = t_distribution.sample(rng);
         let offset = offset_distribution.sample(rng);
@@ -127,7 +164,7 @@ fn write_some_correlation<O: std::io::Write>(
         let weight = 1.max((weight_t * weight_upper_bound_f32) as usize);
 
         // no zero weights!
-        weight_sum += weight;
+        weights.push(weight);
 
         if weight == 0 {
             println!(
@@ -137,23 +174,23 @@ fn write_some_correlation<O: std::io::Write>(
         }
         writeln!(output, "{} {} {}", id, value, weight)?;
     }
-    Ok(weight_sum)
+    Ok(weights)
 }
 
 fn write_strong_correlation<O: std::io::Write>(
     options: &Options,
     output: &mut O,
     rng: &mut ThreadRng,
-) -> Result<usize, Box<dyn std::error::Error>> {
+) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
     let weight_distribution = Uniform::from(options.weight_lower_bound..options.weight_upper_bound);
-    let mut weight_sum = 0;
+    let mut weights = Vec::with_capacity(options.item_count);
     for id in 0..options.item_count {
         let weight = weight_distribution.sample(rng);
         let value = weight + options.value_offset;
 
-        weight_sum += weight;
+        weights.push(weight);
 
         writeln!(output, "{} {} {}", id, value, weight)?;
     }
-    Ok(weight_sum)
+    Ok(weights)
 }
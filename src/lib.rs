@@ -0,0 +1,25 @@
+//! Solvers for the 0-1 knapsack problem.
+//!
+//! The `solve` and `generate` sub-commands of the `rust-knapsack-solver` binary are thin
+//! wrappers around this library, so anything the CLI can do can also be done in-process.
+//!
+//! ```no_run
+//! use rust_knapsack_solver::{minknap, Item, Problem};
+//!
+//! let items = vec![Item::new(0, 10, 5), Item::new(1, 40, 4), Item::new(2, 30, 6)];
+//! let problem: Problem<u64> = Problem::new(items, 10);
+//! let solution = minknap::solve(&problem).unwrap();
+//! assert!(solution.validate(&problem));
+//! ```
+
+mod converter;
+pub mod generate;
+pub mod solver;
+
+pub use solver::problem::{CountLimits, Dimension, Fixing, Item, Problem, Solution, Uncertainty};
+pub use solver::registry::{KnapsackSolver, SolveConfig, SolveError, SolveReport, SolverRegistry};
+pub use solver::{
+    biobjective, bounded, cardinality, conflict, dynamic, expknap, fractional, greedy, k_best,
+    mcknap, minknap, multidim, multiple, precedence, profile, robust, sensitivity, subset_sum,
+    unbounded,
+};
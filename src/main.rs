@@ -1,8 +1,5 @@
 use clap::Parser;
-
-mod converter;
-mod generate;
-mod solver;
+use rust_knapsack_solver::{generate, solver};
 
 #[derive(Parser, Debug)]
 #[clap(version)]
@@ -11,13 +8,18 @@ enum Command {
     Solve(solver::Options),
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn main() {
     let command = Command::parse();
 
-    match command {
-        Command::Generate(options) => generate::run(&options)?,
-        Command::Solve(options) => solver::run(&options)?,
-    }
+    let result = match command {
+        Command::Generate(options) => generate::run(&options),
+        Command::Solve(options) => solver::run(&options),
+    };
 
-    Ok(())
+    // Report errors with their Display message rather than the Debug dump
+    // we would get from returning them
+    if let Err(e) = result {
+        eprintln!("ERROR: {}", e);
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,370 @@
+// Bi-objective knapsack problem, where every item has two values to trade off
+//
+// The solver lists the whole Pareto front, every solution that no other solution matches
+// in both values, following Nemhauser and Ullmann. Items are added one at a time to a list
+// of states ordered by weight, merging the states that leave the item out with those that
+// take it, the way minknap's add_item_t merges its sorted buffers. A state is dominated,
+// and dropped, when a state merged before it weighs no more and is worth at least as much
+// in both values. Equal weights are merged most valuable first, so that a state is never
+// merged before a state that dominates it. The states merged so far are summarised as a
+// staircase, the best second value for each first value, so each check is one lookup.
+//
+// Once every item is added, the front is the states that no other state matches in both
+// values, whatever their weight.
+
+use crate::solver::dynamic::MAX_BYTES;
+use crate::solver::integer::*;
+use crate::solver::problem::*;
+use crate::solver::registry::*;
+use std::collections::BTreeMap;
+use std::mem::size_of;
+
+/// A solution on the Pareto front
+#[derive(Debug)]
+pub struct ParetoPoint<T> {
+    /// The items taken, with their total first value and weight
+    pub solution: Solution<T>,
+    /// Total second value of the items taken
+    pub second_value: T,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct State<T> {
+    value: T,
+    second_value: T,
+    weight: T,
+    /// Index in the trail of the last item taken, None if no item is taken
+    trail: Option<usize>,
+}
+
+impl<T: Integer> State<T> {
+    /// Lightest first, then most valuable first
+    fn merge_order(&self, other: &State<T>) -> std::cmp::Ordering {
+        self.weight
+            .cmp(&other.weight)
+            .then(other.value.cmp(&self.value))
+            .then(other.second_value.cmp(&self.second_value))
+    }
+}
+
+/// An item taken, linked to the item taken before it
+struct TrailStep {
+    item: usize,
+    previous: Option<usize>,
+}
+
+/// Best second value for each first value among the states merged so far,
+/// second values falling as first values rise
+struct Staircase<T> {
+    steps: BTreeMap<T, T>,
+}
+
+impl<T: Integer> Staircase<T> {
+    /// True if some state is worth at least as much in both values
+    fn dominates(&self, value: T, second_value: T) -> bool {
+        // The first step at or past `value` has the best second value of those steps
+        self.steps
+            .range(value..)
+            .next()
+            .is_some_and(|(_, &second)| second >= second_value)
+    }
+
+    /// Add values that are not dominated, and drop the steps they dominate
+    fn insert(&mut self, value: T, second_value: T) {
+        let dominated: Vec<T> = self
+            .steps
+            .range(..=value)
+            .rev()
+            .take_while(|(_, &second)| second <= second_value)
+            .map(|(&value, _)| value)
+            .collect();
+        for value in dominated {
+            self.steps.remove(&value);
+        }
+        self.steps.insert(value, second_value);
+    }
+}
+
+struct Instance<'a, T> {
+    problem: &'a Problem<T>,
+    trail: Vec<TrailStep>,
+    states_explored: usize,
+}
+
+impl<'a, T: Integer> Instance<'a, T> {
+    /// The state taking every item fixed in
+    fn root(&mut self) -> Result<State<T>, SolveError> {
+        let mut root = State {
+            value: T::ZERO,
+            second_value: T::ZERO,
+            weight: T::ZERO,
+            trail: None,
+        };
+        // Problem::check_overflow guarantees the sums of values and weights fit
+        for index in 0..self.problem.items.len() {
+            if self.problem.fixing(index) == Fixing::In {
+                root = self.take(&root, index);
+            }
+        }
+        if root.weight > self.problem.capacity {
+            return Err(SolveError::Infeasible(format!(
+                "the items fixed in weigh {}, over the capacity of {}",
+                root.weight, self.problem.capacity
+            )));
+        }
+        Ok(root)
+    }
+
+    fn take(&mut self, state: &State<T>, index: usize) -> State<T> {
+        let item = &self.problem.items[index];
+        self.trail.push(TrailStep {
+            item: index,
+            previous: state.trail,
+        });
+        State {
+            value: state.value + item.value,
+            second_value: state.second_value + self.problem.second_value(index),
+            weight: state.weight + item.weight,
+            trail: Some(self.trail.len() - 1),
+        }
+    }
+
+    /// Merge the states leaving the item at `index` out with those taking it
+    fn add_item(&mut self, index: usize, states: &[State<T>], next_states: &mut Vec<State<T>>) {
+        let item = self.problem.items[index];
+        let second_value = self.problem.second_value(index);
+        let state_count = states.len();
+        let mut staircase = Staircase {
+            steps: BTreeMap::new(),
+        };
+        next_states.clear();
+
+        let mut keep_index = 0;
+        let mut change_index = 0;
+        while keep_index != state_count || change_index != state_count {
+            let changed = if change_index == state_count {
+                None
+            } else {
+                let state = &states[change_index];
+                // States are ordered by weight, so no later state fits either
+                if state.weight + item.weight > self.problem.capacity {
+                    change_index = state_count;
+                    continue;
+                }
+                // Only states that are kept get a step in the trail, see below
+                Some(State {
+                    value: state.value + item.value,
+                    second_value: state.second_value + second_value,
+                    weight: state.weight + item.weight,
+                    trail: None,
+                })
+            };
+
+            let take_change = match &changed {
+                Some(changed) => {
+                    keep_index == state_count || changed.merge_order(&states[keep_index]).is_lt()
+                }
+                None => false,
+            };
+            let state = if take_change {
+                change_index += 1;
+                changed.unwrap()
+            } else {
+                keep_index += 1;
+                states[keep_index - 1]
+            };
+
+            self.states_explored += 1;
+            if staircase.dominates(state.value, state.second_value) {
+                continue;
+            }
+            staircase.insert(state.value, state.second_value);
+            if take_change {
+                let parent = states[change_index - 1];
+                next_states.push(self.take(&parent, index));
+            } else {
+                next_states.push(state);
+            }
+        }
+    }
+
+    fn decision(&self, state: &State<T>) -> Vec<bool> {
+        let mut decision = vec![false; self.problem.items.len()];
+        let mut step = state.trail;
+        while let Some(index) = step {
+            decision[self.trail[index].item] = true;
+            step = self.trail[index].previous;
+        }
+        decision
+    }
+
+    fn bytes_used(&self, states: &[State<T>], next_states: &[State<T>]) -> usize {
+        (states.len() + next_states.len()) * size_of::<State<T>>()
+            + self.trail.len() * size_of::<TrailStep>()
+    }
+}
+
+/// Every Pareto optimal solution, ordered by rising first value and falling second value.
+/// Solutions worth the same in both values are listed once, with the lightest of them
+pub fn pareto_front<T: Integer>(
+    problem: &Problem<T>,
+    verbose: bool,
+) -> Result<(Vec<ParetoPoint<T>>, usize), SolveError> {
+    problem.check_supported("Biobjective", &[Extension::SecondValues])?;
+    problem.check_overflow()?;
+
+    let mut instance = Instance {
+        problem,
+        trail: Vec::new(),
+        states_explored: 0,
+    };
+    let mut states = vec![instance.root()?];
+    let mut next_states = Vec::new();
+    for index in 0..problem.items.len() {
+        if problem.fixing(index) != Fixing::Free {
+            continue;
+        }
+        instance.add_item(index, &states, &mut next_states);
+        std::mem::swap(&mut states, &mut next_states);
+
+        let bytes_used = instance.bytes_used(&states, &next_states);
+        if bytes_used > MAX_BYTES {
+            return Err(SolveError::TooLarge {
+                size: bytes_used,
+                limit: MAX_BYTES,
+            });
+        }
+    }
+    if verbose {
+        println!(
+            "states left: {}, states explored: {}",
+            states.len(),
+            instance.states_explored
+        );
+    }
+
+    // Most valuable first, so each state is only on the front if its second value
+    // beats every state before it
+    states.sort_by(|a, b| {
+        b.value
+            .cmp(&a.value)
+            .then(b.second_value.cmp(&a.second_value))
+            .then(a.weight.cmp(&b.weight))
+    });
+    let mut front = Vec::new();
+    let mut best_second_value = None;
+    for state in &states {
+        if best_second_value.is_some_and(|best| state.second_value <= best) {
+            continue;
+        }
+        best_second_value = Some(state.second_value);
+        front.push(ParetoPoint {
+            solution: Solution::from_bools(&instance.decision(state), state.value, state.weight),
+            second_value: state.second_value,
+        });
+    }
+    front.reverse();
+    Ok((front, instance.states_explored))
+}
+
+/// Nemhauser-Ullmann enumeration of the Pareto front, reports the point with
+/// the most first value. `pareto_front` returns the whole front
+pub struct Biobjective;
+
+impl<T: Integer> KnapsackSolver<T> for Biobjective {
+    fn name(&self) -> &'static str {
+        "Biobjective"
+    }
+
+    fn solve(
+        &self,
+        problem: &Problem<T>,
+        config: &SolveConfig,
+    ) -> Result<SolveReport<T>, SolveError> {
+        let (front, states_explored) = pareto_front(problem, config.verbose)?;
+        // The front always holds at least the root state
+        let best = front.into_iter().last().unwrap();
+        Ok(SolveReport {
+            solution: best.solution,
+            states_explored: Some(states_explored),
+            bytes_used: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::solver::test_util::{subsets, Lcg};
+
+    /// Pseudo random problem where each item has a second value
+    fn example_problem(item_count: usize, seed: u64) -> Problem<u64> {
+        let mut rng = Lcg::new(seed);
+        let items = rng.items(item_count, 0..30, 0..20);
+        let mut problem = Problem::new(items, 5 * item_count as u64);
+        problem.second_values = Some((0..item_count).map(|_| rng.below(30)).collect());
+        problem
+    }
+
+    /// Values of every Pareto optimal solution, by checking every subset
+    fn brute_force(problem: &Problem<u64>) -> Vec<(u64, u64)> {
+        let points: Vec<(u64, u64)> = subsets(problem)
+            .filter(|(_, _, weight)| *weight <= problem.capacity)
+            .map(|(taken, value, _)| {
+                let second = (0..taken.len())
+                    .filter(|&i| taken[i])
+                    .map(|i| problem.second_value(i))
+                    .sum();
+                (value, second)
+            })
+            .collect();
+        let mut front: Vec<(u64, u64)> = points
+            .iter()
+            .filter(|&&(v, s)| {
+                !points
+                    .iter()
+                    .any(|&(ov, os)| ov >= v && os >= s && (ov, os) != (v, s))
+            })
+            .copied()
+            .collect();
+        front.sort();
+        front.dedup();
+        front
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        for seed in 0..20 {
+            let problem = example_problem(12, seed);
+            let (front, _) = pareto_front(&problem, false).unwrap();
+            for point in &front {
+                assert!(point.solution.validate(&problem));
+                let second: u64 = point
+                    .solution
+                    .decision
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &d)| d * problem.second_value(i))
+                    .sum();
+                assert_eq!(second, point.second_value);
+            }
+            let values: Vec<(u64, u64)> = front
+                .iter()
+                .map(|p| (p.solution.value, p.second_value))
+                .collect();
+            assert_eq!(values, brute_force(&problem), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn best_first_value_is_reported() {
+        let problem = example_problem(15, 3);
+        let solution = Biobjective
+            .solve(&problem, &SolveConfig::default())
+            .unwrap();
+        let mut single = Problem::new(problem.items.clone(), problem.capacity);
+        single.second_values = None;
+        let optimum = crate::solver::dynamic::solve(&single).unwrap().value;
+        assert_eq!(solution.solution.value, optimum);
+    }
+}
@@ -0,0 +1,147 @@
+// Bounded knapsack problem, where each item has a number of available copies
+//
+// Each item is split into 0-1 items holding 1, 2, 4, ... copies, with one final item
+// holding whatever is left over. Any count from 0 up to the available copies can be
+// made from a subset of those items, so solving the split problem with minknap
+// solves the bounded problem, using only O(log(copies)) items per original item.
+
+use crate::solver::integer::*;
+use crate::solver::minknap;
+use crate::solver::problem::*;
+use crate::solver::registry::*;
+
+/// Binary splitting into the 0-1 minknap solver
+pub struct Bounded;
+
+impl<T: Integer> KnapsackSolver<T> for Bounded {
+    fn name(&self) -> &'static str {
+        "Bounded"
+    }
+
+    fn solve(
+        &self,
+        problem: &Problem<T>,
+        config: &SolveConfig,
+    ) -> Result<SolveReport<T>, SolveError> {
+        problem.check_supported("Bounded", &[Extension::Copies])?;
+        if problem.fixings.is_some() {
+            return solve_fixed(self, problem, config);
+        }
+        problem.check_overflow()?;
+
+        let split = SplitProblem::new(problem);
+        let report = minknap::Minknap.solve(&split.problem, config)?;
+        let decision = split.merge_decision(&report.solution.decision);
+        Ok(SolveReport {
+            solution: Solution {
+                decision,
+                value: report.solution.value,
+                weight: report.solution.weight,
+                bins: None,
+            },
+            ..report
+        })
+    }
+}
+
+pub fn solve<T: Integer>(problem: &Problem<T>) -> Result<Solution<T>, SolveError> {
+    let report = Bounded.solve(problem, &SolveConfig::default())?;
+    Ok(report.solution)
+}
+
+/// A 0-1 problem made by splitting up the copies of each item
+struct SplitProblem<T> {
+    problem: Problem<T>,
+    /// For each split item, the index of the original item and how many copies it holds
+    origin: Vec<(usize, T)>,
+    /// Number of items in the original problem, some of which may have no split items
+    item_count: usize,
+}
+
+impl<T: Integer> SplitProblem<T> {
+    fn new(problem: &Problem<T>) -> SplitProblem<T> {
+        let two = T::ONE + T::ONE;
+        let mut items = Vec::new();
+        let mut origin = Vec::new();
+        for (index, item) in problem.items.iter().enumerate() {
+            // No more copies than could ever fit are worth splitting up
+            let mut remaining = problem.usable_copies(index);
+
+            let mut chunk = T::ONE;
+            while remaining > T::ZERO {
+                let copies = chunk.min(remaining);
+                // Problem::check_overflow covers value and weight times copies
+                items.push(Item::new(
+                    item.id,
+                    item.value * copies,
+                    item.weight * copies,
+                ));
+                origin.push((index, copies));
+                remaining -= copies;
+                chunk = chunk.saturating_mul(two);
+            }
+        }
+
+        SplitProblem {
+            problem: Problem::new(items, problem.capacity),
+            origin,
+            item_count: problem.items.len(),
+        }
+    }
+
+    /// Turn a decision for the split problem back into copy counts for the original
+    fn merge_decision(&self, split_decision: &[T]) -> Vec<T> {
+        let mut decision = vec![T::ZERO; self.item_count];
+        for (&(index, copies), &taken) in self.origin.iter().zip(split_decision) {
+            if taken != T::ZERO {
+                decision[index] += copies;
+            }
+        }
+        decision
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::solver::dynamic;
+
+    /// Expand every copy into its own item, so the 0-1 dynamic solver can check us
+    fn expanded_optimum(problem: &Problem<u64>) -> u64 {
+        let mut items = Vec::new();
+        for (index, item) in problem.items.iter().enumerate() {
+            for _ in 0..problem.copies(index) {
+                items.push(*item);
+            }
+        }
+        let expanded = Problem::new(items, problem.capacity);
+        dynamic::solve(&expanded).unwrap().value
+    }
+
+    #[test]
+    fn split_covers_every_count() {
+        let mut problem = Problem::new(vec![Item::new(0, 3u64, 1)], 100);
+        problem.copies = Some(vec![13]);
+        let split = SplitProblem::new(&problem);
+        let chunks: Vec<u64> = split.origin.iter().map(|(_, c)| *c).collect();
+        assert_eq!(chunks, vec![1, 2, 4, 6]);
+    }
+
+    #[test]
+    fn matches_expanded_problem() {
+        let items = vec![
+            Item::new(0, 10u64, 7),
+            Item::new(1, 13, 9),
+            Item::new(2, 4, 3),
+            Item::new(3, 25, 19),
+            Item::new(4, 1, 0),
+        ];
+        let mut problem = Problem::new(items, 50);
+        problem.copies = Some(vec![3, 2, 5, 1, 2]);
+
+        let solution = solve(&problem).unwrap();
+        assert!(solution.validate(&problem));
+        assert_eq!(solution.value, expanded_optimum(&problem));
+        assert_eq!(solution.decision[4], 2);
+    }
+}
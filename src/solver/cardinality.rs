@@ -0,0 +1,472 @@
+// Cardinality constrained knapsack problem, where the number of items taken must fall
+// between a least and a most item count
+//
+// The states of minknap, a weight and a profit, are extended with the number of items
+// taken. A state only dominates another with the same item count, as fewer items may
+// miss the least count and more items may leave no room under the most count.
+// Once a state has reached the least count and can no longer pass the most count, its
+// count no longer matters, so all such states share a count and dominate each other.
+//
+// Every state taking an allowed number of items is a solution, with the items not yet
+// added left out, and the best of them is the lower bound, which starts from the greedy
+// solution. A state is dropped once it can no longer reach the least count, or when its
+// upper bound is no better than the lower bound.
+//
+// The most count is relaxed with a Lagrange multiplier lambda: every item is worth lambda
+// less, and lambda is paid back for every item the state may still take. The linear
+// relaxation of those reduced values is a bound for any lambda, and the lambda giving the
+// tightest bound for the whole problem is found by binary search, as the bound is convex
+// in lambda. Items are added in order of reduced efficiency, so the bound for the items
+// left only needs prefix sums. The value of the most valuable items a state may still
+// take bounds it too.
+
+use crate::converter::*;
+use crate::solver::dynamic::MAX_BYTES;
+use crate::solver::integer::*;
+use crate::solver::problem::*;
+use crate::solver::registry::*;
+use std::mem::size_of;
+
+/// Marks the end of a chain of choices
+const NO_CHOICE: usize = usize::MAX;
+
+/// A link in the chain of items taken by a state
+#[derive(Debug, Copy, Clone)]
+struct Choice {
+    parent: usize,
+    index: usize,
+}
+
+/// Value and weight of the items taken so far, and how many there are
+#[derive(Debug, Copy, Clone)]
+struct State<T> {
+    k: usize,
+    w: T,
+    p: T,
+    choice: usize,
+}
+
+/// Items that fit, most efficient first once `lambda` is taken off every value
+struct Reduced<T> {
+    lambda: T,
+    order: Vec<usize>,
+    /// Weight and value less `lambda`, at least zero, of each item in `order`
+    weights: Vec<T>,
+    values: Vec<T>,
+    /// Sums of the first i weights and reduced values
+    weight_sums: Vec<T>,
+    value_sums: Vec<T>,
+}
+
+impl<T: Integer> Reduced<T> {
+    fn new(problem: &Problem<T>, lambda: T) -> Reduced<T> {
+        // Reduced items, with their index in the problem as the id
+        let mut items: Vec<Item<T>> = problem
+            .items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| item.weight <= problem.capacity)
+            .map(|(index, item)| Item::new(index, item.value.saturating_sub(lambda), item.weight))
+            .collect();
+        items.sort_unstable_by(|a, b| b.efficiency_cmp(a));
+
+        // Problem::check_overflow guarantees every sum fits
+        let mut weight_sums = vec![T::ZERO];
+        let mut value_sums = vec![T::ZERO];
+        for item in &items {
+            weight_sums.push(*weight_sums.last().unwrap() + item.weight);
+            value_sums.push(*value_sums.last().unwrap() + item.value);
+        }
+        Reduced {
+            lambda,
+            order: items.iter().map(|item| item.id).collect(),
+            weights: items.iter().map(|item| item.weight).collect(),
+            values: items.iter().map(|item| item.value).collect(),
+            weight_sums,
+            value_sums,
+        }
+    }
+
+    /// Dantzig bound on the reduced value of the items from position `next` on,
+    /// the whole items that fit in `residual` then part of the next
+    fn linear_bound(&self, next: usize, residual: T) -> T {
+        let limit = self.weight_sums[next] + residual;
+        let end = next + self.weight_sums[next..].partition_point(|&w| w <= limit) - 1;
+        let mut linear = self.value_sums[end] - self.value_sums[next];
+        if end < self.order.len() {
+            let remaining = limit - self.weight_sums[end];
+            linear += mul_div_floor(remaining, self.values[end], self.weights[end]);
+        }
+        linear
+    }
+
+    /// Lagrangian bound on the value of up to `takeable` items from position `next` on,
+    /// each item is worth `lambda` less, but `lambda` is paid back for every item allowed
+    fn bound(&self, next: usize, residual: T, takeable: usize) -> T {
+        let refund = T::from_usize(takeable).map_or(T::MAX, |t| self.lambda.saturating_mul(t));
+        refund.saturating_add(self.linear_bound(next, residual))
+    }
+
+    /// The multiplier giving the tightest bound for the whole problem.
+    /// The bound is convex in the multiplier, so it is found by binary search
+    fn tightest(problem: &Problem<T>, takeable: usize) -> Reduced<T> {
+        let root_bound =
+            |lambda| Reduced::new(problem, lambda).bound(0, problem.capacity, takeable);
+        let mut low = T::ZERO;
+        let mut high = problem
+            .items
+            .iter()
+            .map(|item| item.value)
+            .max()
+            .unwrap_or(T::ZERO);
+        while low < high {
+            let middle = low + (high - low) / (T::ONE + T::ONE);
+            if root_bound(middle + T::ONE) >= root_bound(middle) {
+                high = middle;
+            } else {
+                low = middle + T::ONE;
+            }
+        }
+        Reduced::new(problem, low)
+    }
+}
+
+struct Instance<'a, T: Integer> {
+    problem: &'a Problem<T>,
+    cardinality: CountLimits,
+    /// Items in the order they are added, with the bounds for the items left
+    reduced: Reduced<T>,
+    /// Sum of the i most valuable items
+    top_values: Vec<T>,
+    /// Best state with an allowed number of items
+    best: Option<State<T>>,
+    choices: Vec<Choice>,
+    states_explored: usize,
+    bytes_used: usize,
+    verbose: bool,
+}
+
+impl<'a, T: Integer> Instance<'a, T> {
+    fn new(problem: &'a Problem<T>, cardinality: CountLimits, verbose: bool) -> Instance<'a, T> {
+        let reduced = if cardinality.max < problem.items.len() {
+            Reduced::tightest(problem, cardinality.max)
+        } else {
+            // The most count never binds, plain efficiency order and the Dantzig bound
+            Reduced::new(problem, T::ZERO)
+        };
+
+        let mut values: Vec<T> = reduced
+            .order
+            .iter()
+            .map(|&index| problem.items[index].value)
+            .collect();
+        values.sort_unstable_by(|a, b| b.cmp(a));
+        let mut top_values = vec![T::ZERO];
+        for value in values {
+            top_values.push(*top_values.last().unwrap() + value);
+        }
+
+        Instance {
+            problem,
+            cardinality,
+            reduced,
+            top_values,
+            best: None,
+            choices: Vec::new(),
+            states_explored: 0,
+            bytes_used: 0,
+            verbose,
+        }
+    }
+
+    fn item_count(&self) -> usize {
+        self.reduced.order.len()
+    }
+
+    /// Bound on the value a state can reach, adding items from position `next` in `order`
+    fn upper_bound(&self, state: &State<T>, next: usize) -> T {
+        let residual = self.problem.capacity - state.w;
+        let takeable = (self.cardinality.max - state.k).min(self.item_count() - next);
+        let bound = self.reduced.bound(next, residual, takeable);
+        state.p.saturating_add(bound.min(self.top_values[takeable]))
+    }
+
+    /// Keep the state unless another state is at least as good, or it can not lead to
+    /// anything better than the lower bound
+    /// Returns whether the state was kept
+    fn keep(&mut self, next_states: &mut Vec<State<T>>, state: State<T>, next: usize) -> bool {
+        // Too few items left to reach the least count
+        if state.k + (self.item_count() - next) < self.cardinality.min {
+            return false;
+        }
+        if let Some(last) = next_states.last() {
+            if last.k == state.k && last.p >= state.p {
+                return false;
+            }
+        }
+        if let Some(best) = &self.best {
+            if self.upper_bound(&state, next) <= best.p {
+                return false;
+            }
+        }
+        if self.cardinality.contains(state.k) && self.best.is_none_or(|best| state.p > best.p) {
+            self.best = Some(state);
+        }
+        next_states.push(state);
+        true
+    }
+
+    /// Item count to keep for a state that took `k` items, with items from position `next`
+    /// in `order` left to add. Once the least count is reached and the most count can no
+    /// longer be passed, the count no longer matters, and every such state counts as the
+    /// least count so they can dominate each other
+    fn count_key(&self, k: usize, next: usize) -> usize {
+        if k >= self.cardinality.min && k + (self.item_count() - next) <= self.cardinality.max {
+            self.cardinality.min
+        } else {
+            k
+        }
+    }
+
+    /// States leaving out and taking the item at position `position` in `order`,
+    /// sorted by item count then weight
+    fn add_item(&mut self, position: usize, current_states: &[State<T>]) -> Vec<State<T>> {
+        let index = self.reduced.order[position];
+        let item = self.problem.items[index];
+        let next = position + 1;
+
+        // The parent choice of states taking the item, None for states leaving it out
+        let mut candidates = Vec::with_capacity(current_states.len() * 2);
+        for state in current_states {
+            let mut skip = *state;
+            skip.k = self.count_key(state.k, next);
+            candidates.push((skip, None));
+            if state.k < self.cardinality.max && state.w + item.weight <= self.problem.capacity {
+                let take = State {
+                    k: self.count_key(state.k + 1, next),
+                    w: state.w + item.weight,
+                    p: state.p + item.value,
+                    choice: NO_CHOICE,
+                };
+                candidates.push((take, Some(state.choice)));
+            }
+        }
+        // Among equal weights, the more valuable state goes first
+        candidates.sort_unstable_by(|(a, _), (b, _)| {
+            a.k.cmp(&b.k).then(a.w.cmp(&b.w)).then(b.p.cmp(&a.p))
+        });
+
+        let mut next_states = Vec::with_capacity(candidates.len());
+        for (mut state, parent) in candidates {
+            if let Some(parent) = parent {
+                state.choice = self.choices.len();
+                if self.keep(&mut next_states, state, next) {
+                    self.choices.push(Choice { parent, index });
+                }
+            } else {
+                self.keep(&mut next_states, state, next);
+            }
+        }
+        self.states_explored += next_states.len();
+        next_states
+    }
+
+    /// Start the lower bound from the greedy solution in the order items are added,
+    /// if it takes an allowed number of items
+    fn greedy(&mut self) {
+        let mut state = State {
+            k: 0,
+            w: T::ZERO,
+            p: T::ZERO,
+            choice: NO_CHOICE,
+        };
+        for &index in &self.reduced.order {
+            let item = &self.problem.items[index];
+            if state.k < self.cardinality.max && state.w + item.weight <= self.problem.capacity {
+                self.choices.push(Choice {
+                    parent: state.choice,
+                    index,
+                });
+                state.k += 1;
+                state.w += item.weight;
+                state.p += item.value;
+                state.choice = self.choices.len() - 1;
+            }
+        }
+        if self.cardinality.contains(state.k) {
+            self.best = Some(state);
+        }
+    }
+
+    fn solve(&mut self) -> Result<(), SolveError> {
+        self.greedy();
+        let start = State {
+            k: self.count_key(0, 0),
+            w: T::ZERO,
+            p: T::ZERO,
+            choice: NO_CHOICE,
+        };
+        let mut current_states = Vec::new();
+        self.keep(&mut current_states, start, 0);
+
+        for position in 0..self.item_count() {
+            if current_states.is_empty() {
+                break;
+            }
+            current_states = self.add_item(position, &current_states);
+
+            self.bytes_used = self.bytes_used.max(
+                self.choices.capacity() * size_of::<Choice>()
+                    + current_states.capacity() * size_of::<State<T>>() * 2,
+            );
+            if self.bytes_used > MAX_BYTES {
+                return Err(SolveError::TooLarge {
+                    size: self.bytes_used,
+                    limit: MAX_BYTES,
+                });
+            }
+        }
+
+        if self.verbose {
+            println!(
+                "items: {}, lambda: {}, states_explored: {}, mem_used: {} ({} bytes)",
+                self.item_count(),
+                self.reduced.lambda,
+                self.states_explored,
+                human_readable_bytes(self.bytes_used),
+                self.bytes_used,
+            );
+        }
+        Ok(())
+    }
+
+    fn solution(&self, best: &State<T>) -> Solution<T> {
+        let mut decision = vec![false; self.problem.items.len()];
+        let mut choice = best.choice;
+        while choice != NO_CHOICE {
+            let link = self.choices[choice];
+            decision[link.index] = true;
+            choice = link.parent;
+        }
+        Solution::from_bools(&decision, best.p, best.w)
+    }
+}
+
+/// Minknap style states extended with an item count, for cardinality constrained problems
+pub struct Cardinality;
+
+impl<T: Integer> KnapsackSolver<T> for Cardinality {
+    fn name(&self) -> &'static str {
+        "Cardinality"
+    }
+
+    fn solve(
+        &self,
+        problem: &Problem<T>,
+        config: &SolveConfig,
+    ) -> Result<SolveReport<T>, SolveError> {
+        problem.check_supported("Cardinality", &[Extension::Cardinality])?;
+        if problem.fixings.is_some() {
+            return solve_fixed(self, problem, config);
+        }
+        problem.check_overflow()?;
+        let cardinality = problem.cardinality.unwrap_or(CountLimits {
+            min: 0,
+            max: usize::MAX,
+        });
+        if cardinality.min > cardinality.max {
+            return Err(SolveError::InvalidProblem(format!(
+                "at least {} and at most {} items can not both be taken",
+                cardinality.min, cardinality.max
+            )));
+        }
+
+        let mut instance = Instance::new(problem, cardinality, config.verbose);
+        instance.solve()?;
+        let best = instance.best.ok_or_else(|| {
+            SolveError::Infeasible(format!(
+                "no {} to {} items fit in the capacity",
+                cardinality.min, cardinality.max
+            ))
+        })?;
+        Ok(SolveReport {
+            solution: instance.solution(&best),
+            states_explored: Some(instance.states_explored),
+            bytes_used: Some(instance.bytes_used),
+        })
+    }
+}
+
+pub fn solve<T: Integer>(problem: &Problem<T>) -> Result<Solution<T>, SolveError> {
+    let report = Cardinality.solve(problem, &SolveConfig::default())?;
+    Ok(report.solution)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::solver::minknap;
+    use crate::solver::test_util::subsets;
+
+    fn example_problem(capacity: u64, min: usize, max: usize) -> Problem<u64> {
+        let items = [
+            (78, 18),
+            (35, 9),
+            (89, 23),
+            (36, 20),
+            (94, 59),
+            (75, 61),
+            (74, 70),
+            (79, 75),
+            (80, 76),
+            (16, 30),
+            (3, 0),
+        ]
+        .iter()
+        .enumerate()
+        .map(|(id, &(value, weight))| Item::new(id, value, weight))
+        .collect();
+        let mut problem = Problem::new(items, capacity);
+        problem.cardinality = Some(CountLimits { min, max });
+        problem
+    }
+
+    /// Best value over every subset with an allowed number of items
+    fn brute_force(problem: &Problem<u64>) -> Option<u64> {
+        let cardinality = problem.cardinality.unwrap();
+        subsets(problem)
+            .filter(|(taken, _, weight)| {
+                let count = taken.iter().filter(|&&taken| taken).count();
+                cardinality.contains(count) && *weight <= problem.capacity
+            })
+            .map(|(_, value, _)| value)
+            .max()
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        for capacity in [0, 20, 100, 190, 300, 441] {
+            for (min, max) in [(0, 1), (0, 3), (2, 2), (3, 5), (4, 4), (6, 11), (9, 9)] {
+                let problem = example_problem(capacity, min, max);
+                match brute_force(&problem) {
+                    Some(value) => {
+                        let solution = solve(&problem).unwrap();
+                        assert!(solution.validate(&problem));
+                        assert_eq!(solution.value, value, "{} {} {}", capacity, min, max);
+                    }
+                    None => assert!(matches!(solve(&problem), Err(SolveError::Infeasible(_)))),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unlimited_count_matches_minknap() {
+        let mut problem = example_problem(190, 0, usize::MAX);
+        assert!(solve(&problem).unwrap().validate(&problem));
+        let value = solve(&problem).unwrap().value;
+        problem.cardinality = None;
+        assert_eq!(minknap::solve(&problem).unwrap().value, value);
+        assert_eq!(solve(&problem).unwrap().value, value);
+    }
+}
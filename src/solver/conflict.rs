@@ -0,0 +1,368 @@
+// Knapsack problem with a conflict graph, where some pairs of items can not both be taken
+//
+// Items are ordered by efficiency, as minknap orders them, and the greedy solution takes
+// each item that fits and conflicts with nothing taken so far. Local search then tries each
+// item left out in turn, swapping it in for the items it conflicts with and refilling any
+// room left greedily. Swaps that gain value are kept, until a whole pass gains nothing.
+//
+// Small problems are solved exactly by a depth first branch and bound, starting from the
+// local search solution as the lower bound. Taking an item blocks its neighbours for the
+// rest of the branch, and each branch is bounded by the linear relaxation over the items
+// still open, the Dantzig bound minknap uses. The minknap optimum of the problem without
+// its conflicts bounds every solution, and ends the search early when it is reached.
+
+use crate::solver::integer::*;
+use crate::solver::minknap;
+use crate::solver::problem::*;
+use crate::solver::registry::*;
+
+/// Problems with up to this many items are solved exactly
+pub const EXACT_ITEM_LIMIT: usize = 60;
+
+/// Most passes of the local search over the items left out
+const LOCAL_SEARCH_PASSES: usize = 20;
+
+/// The problem's items with their conflicts
+struct Graph<'a, T> {
+    problem: &'a Problem<T>,
+    /// Items that can be taken on their own, most efficient first
+    order: Vec<usize>,
+    /// Indexed by item, the items it conflicts with
+    neighbours: Vec<Vec<usize>>,
+}
+
+impl<'a, T: Integer> Graph<'a, T> {
+    fn new(problem: &'a Problem<T>) -> Graph<'a, T> {
+        let mut neighbours = vec![Vec::new(); problem.items.len()];
+        for &(first, second) in &problem.conflicts {
+            neighbours[first].push(second);
+            if first != second {
+                neighbours[second].push(first);
+            }
+        }
+
+        // Items heavier than the capacity, or in conflict with themselves, are never taken
+        let mut order: Vec<usize> = (0..problem.items.len())
+            .filter(|&index| problem.items[index].weight <= problem.capacity)
+            .filter(|&index| !neighbours[index].contains(&index))
+            .collect();
+        order.sort_by(|&a, &b| problem.items[b].efficiency_cmp(&problem.items[a]));
+
+        Graph {
+            problem,
+            order,
+            neighbours,
+        }
+    }
+
+    fn item(&self, index: usize) -> &Item<T> {
+        &self.problem.items[index]
+    }
+
+    fn fits(&self, packing: &Packing<T>, index: usize) -> bool {
+        packing.blocked[index] == 0
+            && !packing.taken[index]
+            && self.item(index).weight <= self.problem.capacity - packing.weight
+    }
+
+    // Problem::check_overflow guarantees the sums of values and weights fit
+    fn add(&self, packing: &mut Packing<T>, index: usize) {
+        let item = self.item(index);
+        packing.taken[index] = true;
+        packing.value += item.value;
+        packing.weight += item.weight;
+        for &neighbour in &self.neighbours[index] {
+            packing.blocked[neighbour] += 1;
+        }
+    }
+
+    fn remove(&self, packing: &mut Packing<T>, index: usize) {
+        let item = self.item(index);
+        packing.taken[index] = false;
+        packing.value -= item.value;
+        packing.weight -= item.weight;
+        for &neighbour in &self.neighbours[index] {
+            packing.blocked[neighbour] -= 1;
+        }
+    }
+
+    /// Take every item that still fits, most efficient first, and return them
+    fn fill(&self, packing: &mut Packing<T>) -> Vec<usize> {
+        let mut added = Vec::new();
+        for &index in &self.order {
+            if self.fits(packing, index) {
+                self.add(packing, index);
+                added.push(index);
+            }
+        }
+        added
+    }
+
+    /// Swap the item at `index` in for its neighbours and refill,
+    /// keeping the change only if it gains value
+    fn try_swap(&self, packing: &mut Packing<T>, index: usize) -> bool {
+        let old_value = packing.value;
+        let mut removed = Vec::new();
+        for &neighbour in &self.neighbours[index] {
+            if packing.taken[neighbour] {
+                self.remove(packing, neighbour);
+                removed.push(neighbour);
+            }
+        }
+        let mut added = Vec::new();
+        if self.fits(packing, index) {
+            self.add(packing, index);
+            added.push(index);
+            added.extend(self.fill(packing));
+            if packing.value > old_value {
+                return true;
+            }
+        }
+
+        for &index in &added {
+            self.remove(packing, index);
+        }
+        for &neighbour in &removed {
+            self.add(packing, neighbour);
+        }
+        false
+    }
+
+    fn local_search(&self, packing: &mut Packing<T>) {
+        for _ in 0..LOCAL_SEARCH_PASSES {
+            let mut improved = false;
+            for &index in &self.order {
+                if !packing.taken[index] && self.try_swap(packing, index) {
+                    improved = true;
+                }
+            }
+            if !improved {
+                break;
+            }
+        }
+    }
+}
+
+/// Items taken so far, and how many taken items each item conflicts with
+#[derive(Debug, Clone)]
+struct Packing<T> {
+    taken: Vec<bool>,
+    blocked: Vec<usize>,
+    value: T,
+    weight: T,
+}
+
+impl<T: Integer> Packing<T> {
+    fn empty(item_count: usize) -> Packing<T> {
+        Packing {
+            taken: vec![false; item_count],
+            blocked: vec![0; item_count],
+            value: T::ZERO,
+            weight: T::ZERO,
+        }
+    }
+}
+
+struct BranchAndBound<'a, 'b, T> {
+    graph: &'b Graph<'a, T>,
+    packing: Packing<T>,
+    best_decision: Vec<bool>,
+    best_value: T,
+    /// No solution is worth more than this, so reaching it ends the search
+    upper_bound: T,
+    nodes: usize,
+}
+
+impl<'a, 'b, T: Integer> BranchAndBound<'a, 'b, T> {
+    fn new(graph: &'b Graph<'a, T>, start: Packing<T>, upper_bound: T) -> Self {
+        let item_count = start.taken.len();
+        BranchAndBound {
+            graph,
+            packing: Packing::empty(item_count),
+            best_decision: start.taken,
+            best_value: start.value,
+            upper_bound,
+            nodes: 0,
+        }
+    }
+
+    /// Value of the branch's linear relaxation, taking the open items from `position`
+    /// on in order of efficiency, and the break item's share of the residual capacity
+    fn bound(&self, position: usize) -> T {
+        let mut residual = self.graph.problem.capacity - self.packing.weight;
+        let mut value = self.packing.value;
+        for &index in &self.graph.order[position..] {
+            if self.packing.blocked[index] != 0 {
+                continue;
+            }
+            let item = self.graph.item(index);
+            if item.weight <= residual {
+                residual -= item.weight;
+                value += item.value;
+            } else {
+                return value.saturating_add(mul_div_floor(residual, item.value, item.weight));
+            }
+        }
+        value
+    }
+
+    fn branch(&mut self, position: usize) {
+        self.nodes += 1;
+        if self.packing.value > self.best_value {
+            self.best_value = self.packing.value;
+            self.best_decision.clone_from(&self.packing.taken);
+        }
+        if position == self.graph.order.len()
+            || self.best_value >= self.upper_bound
+            || self.bound(position) <= self.best_value
+        {
+            return;
+        }
+
+        let index = self.graph.order[position];
+        if self.graph.fits(&self.packing, index) {
+            self.graph.add(&mut self.packing, index);
+            self.branch(position + 1);
+            self.graph.remove(&mut self.packing, index);
+        }
+        self.branch(position + 1);
+    }
+}
+
+/// Greedy and local search heuristic, with branch and bound for small problems
+pub struct Conflict;
+
+impl<T: Integer> KnapsackSolver<T> for Conflict {
+    fn name(&self) -> &'static str {
+        "Conflict"
+    }
+
+    fn solve(
+        &self,
+        problem: &Problem<T>,
+        config: &SolveConfig,
+    ) -> Result<SolveReport<T>, SolveError> {
+        problem.check_supported("Conflict", &[Extension::Conflicts])?;
+        if problem.fixings.is_some() {
+            return solve_fixed(self, problem, config);
+        }
+        problem.check_overflow()?;
+
+        let graph = Graph::new(problem);
+        let mut packing = Packing::empty(problem.items.len());
+        graph.fill(&mut packing);
+        let greedy_value = packing.value;
+        graph.local_search(&mut packing);
+
+        let relaxed = Problem::new(problem.items.clone(), problem.capacity);
+        let upper_bound = minknap::solve(&relaxed)?.value;
+        if config.verbose {
+            println!(
+                "conflicts: {}, greedy value: {}, local search value: {}, upper bound without conflicts: {}",
+                problem.conflicts.len(),
+                greedy_value,
+                packing.value,
+                upper_bound
+            );
+        }
+
+        let exact = problem.items.len() <= EXACT_ITEM_LIMIT;
+        let (decision, value, states_explored) = if exact && packing.value < upper_bound {
+            let mut search = BranchAndBound::new(&graph, packing, upper_bound);
+            search.branch(0);
+            if config.verbose {
+                println!("branch and bound nodes: {}", search.nodes);
+            }
+            (search.best_decision, search.best_value, Some(search.nodes))
+        } else {
+            (packing.taken, packing.value, None)
+        };
+
+        let weight = decision
+            .iter()
+            .zip(&problem.items)
+            .filter(|(&taken, _)| taken)
+            .map(|(_, item)| item.weight)
+            .sum();
+        Ok(SolveReport {
+            solution: Solution::from_bools(&decision, value, weight),
+            states_explored,
+            bytes_used: None,
+        })
+    }
+}
+
+pub fn solve<T: Integer>(problem: &Problem<T>) -> Result<Solution<T>, SolveError> {
+    let report = Conflict.solve(problem, &SolveConfig::default())?;
+    Ok(report.solution)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::solver::test_util::{subsets, Lcg};
+
+    /// Pseudo random problem, with each pair of items in conflict one time in `sparsity`
+    fn example_problem(item_count: usize, sparsity: u64, seed: u64) -> Problem<u64> {
+        let mut rng = Lcg::new(seed);
+        let items = rng.items(item_count, 1..51, 0..30);
+        let mut problem = Problem::new(items, 6 * item_count as u64);
+        for first in 0..item_count {
+            for second in first + 1..item_count {
+                if rng.below(sparsity) == 0 {
+                    problem.conflicts.push((first, second));
+                }
+            }
+        }
+        problem
+    }
+
+    fn brute_force(problem: &Problem<u64>) -> u64 {
+        subsets(problem)
+            .filter(|(taken, _, weight)| {
+                *weight <= problem.capacity
+                    && !problem.conflicts.iter().any(|&(a, b)| taken[a] && taken[b])
+            })
+            .map(|(_, value, _)| value)
+            .max()
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        for seed in 0..20 {
+            let problem = example_problem(14, 1 + seed % 4, seed);
+            let solution = solve(&problem).unwrap();
+            assert!(solution.validate(&problem));
+            assert_eq!(solution.value, brute_force(&problem), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn local_search_on_large_graphs() {
+        let problem = example_problem(300, 20, 3);
+        let solution = solve(&problem).unwrap();
+        assert!(solution.validate(&problem));
+
+        // The local search never ends up below the greedy solution
+        let graph = Graph::new(&problem);
+        let mut packing = Packing::empty(problem.items.len());
+        graph.fill(&mut packing);
+        assert!(solution.value >= packing.value);
+    }
+
+    #[test]
+    fn swap_beats_greedy() {
+        // Greedy takes item 0 first, which blocks both other items
+        let items = vec![
+            Item::new(0, 30u32, 2),
+            Item::new(1, 20, 2),
+            Item::new(2, 20, 2),
+        ];
+        let mut problem = Problem::new(items, 4);
+        problem.conflicts = vec![(0, 1), (2, 0)];
+        let solution = solve(&problem).unwrap();
+        assert_eq!(solution.decision, vec![0, 1, 1]);
+        assert_eq!(solution.value, 40);
+    }
+}
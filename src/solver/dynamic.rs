@@ -1,56 +1,107 @@
+use crate::solver::integer::*;
 use crate::solver::problem::*;
+use crate::solver::registry::*;
 
 // Like 8gb
-pub const MAX_STATES: usize = (8 * 1000000000) / std::mem::size_of::<usize>();
+pub const MAX_BYTES: usize = 8 * 1000000000;
 
-struct Array {
-    data: Vec<usize>,
+/// Most table entries we are willing to allocate for a given integer type
+pub fn max_states<T>() -> usize {
+    MAX_BYTES / std::mem::size_of::<T>()
+}
+
+/// Table of values, `width` columns per row
+pub(crate) struct Array<T> {
+    data: Vec<T>,
     width: usize,
 }
 
-impl Array {
-    fn new(width: usize, height: usize) -> Array {
-        let size = width * height;
-        if size > MAX_STATES {
-            panic!("Problem exceeds size limit: {} > {}", size, MAX_STATES);
+impl<T: Integer> Array<T> {
+    pub(crate) fn new(width: usize, height: usize, verbose: bool) -> Result<Array<T>, SolveError> {
+        let size = width.saturating_mul(height);
+        if size > max_states::<T>() {
+            return Err(SolveError::TooLarge {
+                size,
+                limit: max_states::<T>(),
+            });
         }
 
-        println!("Array size: {}", size);
+        if verbose {
+            println!("Array size: {}", size);
+        }
 
-        Array {
-            data: vec![0; size],
+        Ok(Array {
+            data: vec![T::ZERO; size],
             width,
-        }
+        })
     }
 
     fn index(&self, x: usize, y: usize) -> usize {
         y * self.width + x
     }
 
-    fn get(&self, x: usize, y: usize) -> usize {
+    pub(crate) fn get(&self, x: usize, y: usize) -> T {
         let index = self.index(x, y);
         self.data[index]
     }
 
-    fn set(&mut self, x: usize, y: usize, v: usize) {
+    pub(crate) fn set(&mut self, x: usize, y: usize, v: T) {
         let index = self.index(x, y);
         self.data[index] = v
     }
 
-    fn last(&self) -> usize {
+    fn last(&self) -> T {
         *self.data.last().unwrap()
     }
 }
 
-pub fn solve(problem: &Problem) -> Solution {
-    let width = problem.capacity + 1;
+/// Classic table based dynamic programming solver
+pub struct Dynamic;
+
+impl<T: Integer> KnapsackSolver<T> for Dynamic {
+    fn name(&self) -> &'static str {
+        "Dynamic"
+    }
+
+    fn solve(
+        &self,
+        problem: &Problem<T>,
+        config: &SolveConfig,
+    ) -> Result<SolveReport<T>, SolveError> {
+        let solution = solve_with_config(problem, config)?;
+        Ok(SolveReport::new(solution))
+    }
+}
+
+pub fn solve<T: Integer>(problem: &Problem<T>) -> Result<Solution<T>, SolveError> {
+    solve_with_config(problem, &SolveConfig::default())
+}
+
+fn solve_with_config<T: Integer>(
+    problem: &Problem<T>,
+    config: &SolveConfig,
+) -> Result<Solution<T>, SolveError> {
+    problem.check_supported("Dynamic", &[Extension::Covering])?;
+    if problem.covering {
+        return Ok(solve_covering(&Dynamic, problem, config)?.solution);
+    }
+    if problem.fixings.is_some() {
+        return Ok(solve_fixed(&Dynamic, problem, config)?.solution);
+    }
+    problem.check_overflow()?;
+    let width = match problem.capacity.to_usize() {
+        Some(capacity) => capacity.saturating_add(1),
+        None => usize::MAX,
+    };
     let height = problem.items.len() + 1;
-    let mut sum_array = Array::new(width, height);
+    let mut sum_array = Array::new(width, height, config.verbose)?;
 
     // This outer loop is looping over items
     // BUT with 1 indexing so recusion works
     for y in 1..height {
         let item = &problem.items[y - 1];
+        // Anything too big for a usize is certainly too big for the table
+        let item_weight = item.weight.to_usize().unwrap_or(usize::MAX);
         // This loop is iterating over weights
         for x in 0..width {
             // Not taking y would be same be same value as
@@ -59,10 +110,10 @@ pub fn solve(problem: &Problem) -> Solution {
 
             // Other wise, find value for previous items
             // without weight of item we're gonna take
-            let do_take = if item.weight <= x {
-                sum_array.get(x - item.weight, y - 1) + item.value
+            let do_take = if item_weight <= x {
+                sum_array.get(x - item_weight, y - 1) + item.value
             } else {
-                0
+                T::ZERO
             };
             let new_value = do_take.max(do_not_take);
             sum_array.set(x, y, new_value);
@@ -71,24 +122,25 @@ pub fn solve(problem: &Problem) -> Solution {
 
     // Back track to make decision vector
     let mut decision = vec![false; problem.items.len()];
-    let mut x = problem.capacity;
+    let mut x = width - 1;
     for y in (1..height).rev() {
         if sum_array.get(x, y) != sum_array.get(x, y - 1) {
             decision[y - 1] = true;
-            x -= problem.items[y - 1].weight;
+            // Only items that fit in the table can be taken
+            x -= problem.items[y - 1].weight.to_usize().unwrap();
         }
     }
 
-    let mut weight_sum = 0;
+    let mut weight_sum = T::ZERO;
     for (d, i) in decision.iter().zip(problem.items.iter()) {
         if *d {
             weight_sum += i.weight;
         }
     }
 
-    Solution {
-        decision,
-        value: sum_array.last(),
-        weight: weight_sum,
-    }
+    Ok(Solution::from_bools(
+        &decision,
+        sum_array.last(),
+        weight_sum,
+    ))
 }
@@ -0,0 +1,273 @@
+// Pisinger's expanding core branch and bound for the 0-1 knapsack problem
+//
+// Like minknap, expknap starts from the break solution and only changes it close to the
+// break item, but it explores the changes depth first rather than as a list of states.
+// A branch under capacity tries adding each item after the core, most efficient first,
+// and a branch over capacity tries removing each item before the core, least efficient
+// first. Either way the next item's efficiency bounds the branch: the value of the branch
+// plus or minus the next item's share of the capacity left or overfilled. Every item past
+// it is less efficient, so once the bound is no better than the best solution found,
+// the whole branch and every later sibling are dropped.
+//
+// The core only grows as far as a branch reaches, so most items are never looked at beyond
+// sorting. Only the current path is kept in memory, where minknap keeps every state of the
+// core, but nothing stops the search from revisiting the same weight and value many times.
+
+use crate::solver::integer::*;
+use crate::solver::minknap::{
+    break_solution, efficiency_ordering, EfficiencyOrdering, ItemEfficiency,
+};
+use crate::solver::problem::*;
+use crate::solver::registry::*;
+use std::cmp::Ordering;
+use std::mem::size_of;
+
+/// A branch on the path from the break solution, one item changed from its parent
+#[derive(Debug, Clone, Copy)]
+struct Branch<T> {
+    /// The next item to remove is the one before `s` in the efficiency ordering
+    s: usize,
+    /// The next item to add is the one at `t` in the efficiency ordering
+    t: usize,
+    value: T,
+    weight: T,
+    /// Position in the efficiency ordering of the item this branch changed,
+    /// None for the break solution
+    changed: Option<usize>,
+}
+
+struct Instance<T> {
+    /// Free items that fit, most efficient first
+    items: Vec<ItemEfficiency<T>>,
+    capacity: T,
+    /// Value and weight of the best solution found, among the ordered items
+    best_value: T,
+    best_weight: T,
+    /// Items the best solution changes from the break solution
+    best_changes: Vec<usize>,
+    /// No solution is worth more than the Dantzig bound, so reaching it ends the search
+    upper_bound: T,
+    nodes: usize,
+    deepest_path: usize,
+}
+
+impl<T: Integer> Instance<T> {
+    /// True if adding a fraction of the item at `t` can not beat the best solution
+    fn add_bound_fails(&self, branch: &Branch<T>, t: usize) -> bool {
+        let item = &self.items[t];
+        let room = self.capacity - branch.weight;
+        branch
+            .value
+            .saturating_add(mul_div_floor(room, item.value, item.weight))
+            <= self.best_value
+    }
+
+    /// True if removing a fraction of the item before `s` can not beat the best solution
+    fn remove_bound_fails(&self, branch: &Branch<T>, s: usize) -> bool {
+        if branch.value <= self.best_value {
+            return true;
+        }
+        // The bound is value - overfill * p / w, beating the best means exceeding it by a
+        // whole unit, so compare overfill * p with (value - best - 1) * w exactly
+        let item = &self.items[s - 1];
+        let overfill = branch.weight - self.capacity;
+        let margin = branch.value - self.best_value - T::ONE;
+        cmp_products(overfill, item.value, margin, item.weight) == Ordering::Greater
+    }
+
+    fn record_if_better(&mut self, path: &[Branch<T>]) {
+        let branch = path.last().unwrap();
+        if branch.weight <= self.capacity && branch.value > self.best_value {
+            self.best_value = branch.value;
+            self.best_weight = branch.weight;
+            self.best_changes = path.iter().filter_map(|b| b.changed).collect();
+        }
+    }
+
+    /// Depth first search from the break solution, the path kept as an explicit stack
+    /// so deep cores can not overflow the call stack
+    fn search(&mut self, root: Branch<T>) {
+        let mut path = vec![root];
+        while let Some(&branch) = path.last() {
+            if self.best_value >= self.upper_bound {
+                break;
+            }
+            let child = if branch.weight <= self.capacity {
+                let t = branch.t;
+                if t == self.items.len() || self.add_bound_fails(&branch, t) {
+                    None
+                } else {
+                    let item = &self.items[t];
+                    // Leaving the item out is the next sibling, from this same branch
+                    path.last_mut().unwrap().t += 1;
+                    Some(Branch {
+                        s: branch.s,
+                        t: t + 1,
+                        value: branch.value + item.value,
+                        weight: branch.weight + item.weight,
+                        changed: Some(t),
+                    })
+                }
+            } else {
+                let s = branch.s;
+                if s == 0 || self.remove_bound_fails(&branch, s) {
+                    None
+                } else {
+                    let item = &self.items[s - 1];
+                    path.last_mut().unwrap().s -= 1;
+                    Some(Branch {
+                        s: s - 1,
+                        t: branch.t,
+                        value: branch.value - item.value,
+                        weight: branch.weight - item.weight,
+                        changed: Some(s - 1),
+                    })
+                }
+            };
+
+            match child {
+                Some(child) => {
+                    self.nodes += 1;
+                    path.push(child);
+                    self.deepest_path = self.deepest_path.max(path.len());
+                    self.record_if_better(&path);
+                }
+                None => {
+                    path.pop();
+                }
+            }
+        }
+    }
+}
+
+/// Pisinger's expanding core branch and bound, exploring changes to the break solution
+/// depth first
+pub struct Expknap;
+
+impl<T: Integer> KnapsackSolver<T> for Expknap {
+    fn name(&self) -> &'static str {
+        "Expknap"
+    }
+
+    fn solve(
+        &self,
+        problem: &Problem<T>,
+        config: &SolveConfig,
+    ) -> Result<SolveReport<T>, SolveError> {
+        problem.check_supported("Expknap", &[Extension::Covering])?;
+        if problem.covering {
+            return solve_covering(self, problem, config);
+        }
+        problem.check_overflow()?;
+
+        let EfficiencyOrdering {
+            items: item_efficiencies,
+            mut decision,
+            base_value,
+            base_weight,
+            capacity,
+        } = efficiency_ordering(problem)?;
+        let break_solution = break_solution(capacity, &item_efficiencies, &mut decision);
+        let break_item = break_solution.break_item;
+
+        let mut instance = Instance {
+            items: item_efficiencies,
+            capacity,
+            best_value: break_solution.profit,
+            best_weight: break_solution.weight,
+            best_changes: Vec::new(),
+            upper_bound: break_solution.linear_profit.max(break_solution.profit),
+            nodes: 0,
+            deepest_path: 1,
+        };
+        instance.search(Branch {
+            s: break_item,
+            t: break_item,
+            value: break_solution.profit,
+            weight: break_solution.weight,
+            changed: None,
+        });
+
+        for &position in &instance.best_changes {
+            let index = instance.items[position].index;
+            decision[index] = !decision[index];
+        }
+        let bytes_used = instance.deepest_path * size_of::<Branch<T>>()
+            + decision.capacity() * size_of::<bool>()
+            + instance.items.capacity() * size_of::<ItemEfficiency<T>>();
+        if config.verbose {
+            println!(
+                "break item: {}, branch and bound nodes: {}, deepest path: {}",
+                break_item, instance.nodes, instance.deepest_path
+            );
+        }
+        Ok(SolveReport {
+            solution: Solution::from_bools(
+                &decision,
+                base_value + instance.best_value,
+                base_weight + instance.best_weight,
+            ),
+            states_explored: Some(instance.nodes),
+            bytes_used: Some(bytes_used),
+        })
+    }
+}
+
+pub fn solve<T: Integer>(problem: &Problem<T>) -> Result<Solution<T>, SolveError> {
+    let report = Expknap.solve(problem, &SolveConfig::default())?;
+    Ok(report.solution)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::solver::test_util::Lcg;
+    use crate::solver::{dynamic, minknap};
+
+    /// Pseudo random problem, with weights and values uncorrelated
+    fn example_problem(item_count: usize, seed: u64) -> Problem<u64> {
+        let items = Lcg::new(seed).items(item_count, 0..1000, 0..1000);
+        Problem::new(items, 250 * item_count as u64)
+    }
+
+    #[test]
+    fn matches_dynamic() {
+        for seed in 0..20 {
+            let problem = example_problem(40, seed);
+            let solution = solve(&problem).unwrap();
+            assert!(solution.validate(&problem));
+            assert_eq!(
+                solution.value,
+                dynamic::solve(&problem).unwrap().value,
+                "seed {}",
+                seed
+            );
+        }
+    }
+
+    #[test]
+    fn matches_minknap_on_large_problems() {
+        let problem = example_problem(5000, 11);
+        let solution = solve(&problem).unwrap();
+        assert!(solution.validate(&problem));
+        assert_eq!(solution.value, minknap::solve(&problem).unwrap().value);
+    }
+
+    #[test]
+    fn edge_cases() {
+        // Every item fits
+        let items = vec![
+            Item::new(0, 5u32, 3),
+            Item::new(1, 4, 0),
+            Item::new(2, 9, 8),
+        ];
+        let problem = Problem::new(items.clone(), 20);
+        assert_eq!(solve(&problem).unwrap().value, 18);
+
+        // No item fits, but the weightless one
+        let problem = Problem::new(items, 2);
+        let solution = solve(&problem).unwrap();
+        assert!(solution.validate(&problem));
+        assert_eq!(solution.value, 4);
+    }
+}
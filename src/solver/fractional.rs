@@ -0,0 +1,265 @@
+// Continuous knapsack problem, where any fraction of an item may be taken
+//
+// Dantzig showed that taking items whole in order of efficiency, until one no longer fits,
+// then filling the rest of the capacity with a fraction of that break item is optimal.
+// This is the linear relaxation minknap bounds with, and it is found with the same
+// efficiency ordering and break solution. Like minknap, items fixed in and weightless
+// items are always taken, and items fixed out are not. Unlike minknap, items heavier than
+// the capacity left are kept, as the relaxation may take part of one.
+//
+// The fraction taken is the residual capacity over the break item's weight, and the
+// value it adds is kept as an exact quotient and remainder, so no precision is lost
+// whatever the integer type.
+
+use crate::solver::integer::*;
+use crate::solver::minknap::{break_solution, relaxation_ordering, EfficiencyOrdering};
+use crate::solver::problem::*;
+use crate::solver::registry::*;
+
+/// Exact rational number, always in lowest terms
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fraction<T> {
+    pub numerator: T,
+    pub denominator: T,
+}
+
+impl<T: Integer> Fraction<T> {
+    /// Panics if `denominator` is zero
+    pub fn new(numerator: T, denominator: T) -> Fraction<T> {
+        assert!(denominator != T::ZERO, "fraction with a zero denominator");
+        let divisor = gcd(numerator, denominator);
+        Fraction {
+            numerator: numerator / divisor,
+            denominator: denominator / divisor,
+        }
+    }
+
+    pub fn zero() -> Fraction<T> {
+        Fraction::new(T::ZERO, T::ONE)
+    }
+}
+
+impl<T: Integer> std::fmt::Display for Fraction<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.denominator == T::ONE {
+            write!(f, "{}", self.numerator)
+        } else {
+            write!(f, "{}/{}", self.numerator, self.denominator)
+        }
+    }
+}
+
+fn gcd<T: Integer>(mut a: T, mut b: T) -> T {
+    while b != T::ZERO {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+    a
+}
+
+/// Optimum of the linear relaxation
+#[derive(Debug)]
+pub struct Relaxation<T> {
+    /// The items taken whole, a feasible 0-1 solution
+    pub solution: Solution<T>,
+    /// Index of the item taken in part, None if no item is split
+    pub break_item: Option<usize>,
+    /// Share of the break item taken, strictly between 0 and 1 when there is one
+    pub fraction: Fraction<T>,
+    /// Whole part of the relaxation's value
+    pub value: T,
+    /// Part of the relaxation's value past `value`, below 1
+    pub value_remainder: Fraction<T>,
+}
+
+impl<T: Integer> Relaxation<T> {
+    /// Smallest integer no less than the relaxation's value,
+    /// an upper bound on the 0-1 optimum
+    pub fn upper_bound(&self) -> T {
+        if self.value_remainder.numerator == T::ZERO {
+            self.value
+        } else {
+            self.value + T::ONE
+        }
+    }
+}
+
+impl<T: Integer> std::fmt::Display for Relaxation<T> {
+    /// The value as a mixed number
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.value_remainder.numerator == T::ZERO {
+            write!(f, "{}", self.value)
+        } else {
+            write!(f, "{} {}", self.value, self.value_remainder)
+        }
+    }
+}
+
+/// Solve the linear relaxation of a 0-1 problem
+pub fn relax<T: Integer>(problem: &Problem<T>) -> Result<Relaxation<T>, SolveError> {
+    problem.check_supported("Fractional", &[])?;
+    problem.check_overflow()?;
+
+    let EfficiencyOrdering {
+        items: item_efficiencies,
+        mut decision,
+        base_value,
+        base_weight,
+        capacity,
+    } = relaxation_ordering(problem)?;
+    let break_solution = break_solution(capacity, &item_efficiencies, &mut decision);
+    let mut value = base_value + break_solution.profit;
+    let mut weight = base_weight + break_solution.weight;
+
+    let mut break_item = None;
+    let mut fraction = Fraction::zero();
+    let mut value_remainder = Fraction::zero();
+    if let Some(item) = item_efficiencies.get(break_solution.break_item) {
+        let residual = problem.capacity - weight;
+        if residual == item.weight {
+            // The break solution stops at an item that fits exactly, take it whole
+            decision[item.index] = true;
+            value += item.value;
+            weight += item.weight;
+        } else if residual != T::ZERO {
+            // residual < weight, so the quotient is below the break item's value
+            let (quotient, remainder) = mul_div_rem(
+                residual.to_u128(),
+                item.value.to_u128(),
+                item.weight.to_u128(),
+            )
+            .unwrap();
+            break_item = Some(item.index);
+            fraction = Fraction::new(residual, item.weight);
+            value += T::from_u128_saturating(quotient);
+            value_remainder = Fraction::new(T::from_u128_saturating(remainder), item.weight);
+        }
+    }
+
+    let whole_value = decision
+        .iter()
+        .zip(&problem.items)
+        .filter(|(&taken, _)| taken)
+        .map(|(_, item)| item.value)
+        .sum();
+    Ok(Relaxation {
+        solution: Solution::from_bools(&decision, whole_value, weight),
+        break_item,
+        fraction,
+        value,
+        value_remainder,
+    })
+}
+
+/// Dantzig's greedy solution to the linear relaxation,
+/// reports the items it takes whole
+pub struct Fractional;
+
+impl<T: Integer> KnapsackSolver<T> for Fractional {
+    fn name(&self) -> &'static str {
+        "Fractional"
+    }
+
+    fn solve(
+        &self,
+        problem: &Problem<T>,
+        config: &SolveConfig,
+    ) -> Result<SolveReport<T>, SolveError> {
+        let relaxation = relax(problem)?;
+        if config.verbose {
+            match relaxation.break_item {
+                Some(index) => println!(
+                    "break item: {}, fraction taken: {}, linear value: {}",
+                    problem.items[index].id, relaxation.fraction, relaxation
+                ),
+                None => println!("no item is split, linear value: {}", relaxation),
+            }
+        }
+        Ok(SolveReport::new(relaxation.solution))
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn fractions_are_reduced() {
+        let half = Fraction::new(6u32, 12);
+        assert_eq!((half.numerator, half.denominator), (1, 2));
+        assert_eq!(half.to_string(), "1/2");
+        assert_eq!(Fraction::new(0u32, 7).to_string(), "0");
+    }
+
+    #[test]
+    fn splits_the_break_item() {
+        // Ordered by efficiency: 3 (50/3), 1 (40/4), 2 (30/6), 0 (10/5)
+        let items = vec![
+            Item::new(0, 10u64, 5),
+            Item::new(1, 40, 4),
+            Item::new(2, 30, 6),
+            Item::new(3, 50, 3),
+        ];
+        let problem = Problem::new(items, 10);
+        let relaxation = relax(&problem).unwrap();
+        assert!(relaxation.solution.validate(&problem));
+        assert_eq!(relaxation.solution.value, 90);
+        assert_eq!(relaxation.break_item, Some(2));
+        assert_eq!(relaxation.fraction, Fraction::new(1, 2));
+        assert_eq!(relaxation.value, 105);
+        assert_eq!(relaxation.to_string(), "105");
+
+        let problem = Problem::new(problem.items, 9);
+        let relaxation = relax(&problem).unwrap();
+        assert_eq!(relaxation.fraction, Fraction::new(1, 3));
+        assert_eq!(relaxation.to_string(), "100");
+
+        let problem = Problem::new(problem.items, 8);
+        let relaxation = relax(&problem).unwrap();
+        assert_eq!(relaxation.fraction, Fraction::new(1, 6));
+        assert_eq!(relaxation.to_string(), "95");
+        assert_eq!(relaxation.upper_bound(), 95);
+    }
+
+    #[test]
+    fn splits_an_item_heavier_than_the_capacity() {
+        let items = vec![Item::new(0, 100u32, 20), Item::new(1, 1, 1)];
+        let problem = Problem::new(items, 10);
+        let relaxation = relax(&problem).unwrap();
+        assert!(relaxation.solution.validate(&problem));
+        assert_eq!(relaxation.solution.value, 0);
+        assert_eq!(relaxation.break_item, Some(0));
+        assert_eq!(relaxation.fraction, Fraction::new(1, 2));
+        assert_eq!(relaxation.to_string(), "50");
+    }
+
+    #[test]
+    fn exact_with_wide_values() {
+        let items = vec![Item::new(0, u128::MAX / 2, 1), Item::new(1, 1 << 126, 7)];
+        let relaxation = relax(&Problem::new(items, 7)).unwrap();
+        assert_eq!(relaxation.fraction, Fraction::new(6, 7));
+        // 6 * 2^126 does not fit in a u128, it is 3 * (2^127 - 2) + 6 and 7 divides 2^127 - 2
+        let quotient = ((1u128 << 127) - 2) / 7 * 3;
+        assert_eq!(relaxation.value, u128::MAX / 2 + quotient);
+        assert_eq!(relaxation.value_remainder, Fraction::new(6, 7));
+        assert_eq!(relaxation.upper_bound(), relaxation.value + 1);
+    }
+
+    #[test]
+    fn no_split_when_everything_fits() {
+        let items = vec![
+            Item::new(0, 4u32, 2),
+            Item::new(1, 3, 0),
+            Item::new(2, 5, 3),
+        ];
+        let relaxation = relax(&Problem::new(items.clone(), 5)).unwrap();
+        assert_eq!(relaxation.break_item, None);
+        assert_eq!(relaxation.value, 12);
+        assert_eq!(relaxation.solution.value, 12);
+
+        let relaxation = relax(&Problem::new(items, 10)).unwrap();
+        assert_eq!(relaxation.break_item, None);
+        assert_eq!(relaxation.value, 12);
+    }
+}
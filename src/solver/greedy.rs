@@ -1,40 +1,65 @@
+use crate::solver::integer::*;
+use crate::solver::precedence::Precedences;
 use crate::solver::problem::*;
+use crate::solver::registry::*;
 
-struct RatioItem {
-    index: usize,
-    ratio: f32,
+/// Takes items in order of efficiency while they fit,
+/// then repairs the solution to respect any precedences
+pub struct Greedy;
+
+impl<T: Integer> KnapsackSolver<T> for Greedy {
+    fn name(&self) -> &'static str {
+        "Greedy"
+    }
+
+    fn solve(
+        &self,
+        problem: &Problem<T>,
+        _config: &SolveConfig,
+    ) -> Result<SolveReport<T>, SolveError> {
+        Ok(SolveReport::new(solve(problem)?))
+    }
 }
 
-pub fn solve(problem: &Problem) -> Solution {
-    let mut ratios: Vec<RatioItem> = problem
-        .items
-        .iter()
-        .enumerate()
-        .map(|(index, item)| RatioItem {
-            index,
-            ratio: item.value as f32 / item.weight as f32,
-        })
-        .collect();
+pub fn solve<T: Integer>(problem: &Problem<T>) -> Result<Solution<T>, SolveError> {
+    problem.check_supported("Greedy", &[Extension::Covering, Extension::Precedences])?;
+    if problem.covering {
+        let report = solve_covering(&Greedy, problem, &SolveConfig::default())?;
+        return Ok(report.solution);
+    }
+    if problem.fixings.is_some() {
+        let report = solve_fixed(&Greedy, problem, &SolveConfig::default())?;
+        return Ok(report.solution);
+    }
+    problem.check_overflow()?;
+    let items = &problem.items;
+    let mut order: Vec<usize> = (0..items.len()).collect();
 
     // We want Highest ratio to lowest
     // Hence b cmp a
-    ratios.sort_unstable_by(|a, b| b.ratio.partial_cmp(&a.ratio).unwrap());
+    order.sort_unstable_by(|&a, &b| items[b].efficiency_cmp(&items[a]));
 
     let mut decision = vec![false; problem.items.len()];
-    let mut weight_sum = 0;
-    let mut value_sum = 0;
-    for r in &ratios {
-        let item = &problem.items[r.index];
+    let mut weight_sum = T::ZERO;
+    let mut value_sum = T::ZERO;
+    for &index in &order {
+        let item = &problem.items[index];
         if weight_sum + item.weight < problem.capacity {
             weight_sum += item.weight;
             value_sum += item.value;
-            decision[r.index] = true;
+            decision[index] = true;
         }
     }
 
-    Solution {
-        decision,
-        value: value_sum,
-        weight: weight_sum,
+    if !problem.precedences.is_empty() {
+        Precedences::new(problem)?.repair(problem, &order, &mut decision);
+        weight_sum = T::ZERO;
+        value_sum = T::ZERO;
+        for (item, _) in problem.items.iter().zip(&decision).filter(|(_, &d)| d) {
+            weight_sum += item.weight;
+            value_sum += item.value;
+        }
     }
+
+    Ok(Solution::from_bools(&decision, value_sum, weight_sum))
 }
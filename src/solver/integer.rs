@@ -0,0 +1,219 @@
+// Problems, items and solvers are generic over the integer type used for
+// weights and profits. Smaller types reduce the memory used by solver state
+// buffers, while u128 allows instances whose sums would not fit in 64 bits.
+
+use std::cmp::Ordering;
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, Mul, Rem, Sub, SubAssign};
+use std::str::FromStr;
+
+/// Unsigned integer type usable for item weights and values
+pub trait Integer:
+    Copy
+    + Debug
+    + Display
+    + Default
+    + Ord
+    + Hash
+    + FromStr
+    + Sum
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Rem<Output = Self>
+    + AddAssign
+    + SubAssign
+    + Send
+    + Sync
+    + 'static
+{
+    const ZERO: Self;
+    const ONE: Self;
+    const MAX: Self;
+
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+    fn saturating_add(self, rhs: Self) -> Self;
+    fn saturating_sub(self, rhs: Self) -> Self;
+    fn saturating_mul(self, rhs: Self) -> Self;
+
+    fn to_usize(self) -> Option<usize>;
+    fn from_usize(v: usize) -> Option<Self>;
+
+    fn to_u128(self) -> u128;
+    /// Values too large for the type become `MAX`
+    fn from_u128_saturating(v: u128) -> Self;
+}
+
+macro_rules! impl_integer {
+    ($($t:ty),*) => {
+        $(
+            impl Integer for $t {
+                const ZERO: $t = 0;
+                const ONE: $t = 1;
+                const MAX: $t = <$t>::MAX;
+
+                fn checked_add(self, rhs: $t) -> Option<$t> {
+                    <$t>::checked_add(self, rhs)
+                }
+
+                fn checked_sub(self, rhs: $t) -> Option<$t> {
+                    <$t>::checked_sub(self, rhs)
+                }
+
+                fn checked_mul(self, rhs: $t) -> Option<$t> {
+                    <$t>::checked_mul(self, rhs)
+                }
+
+                fn saturating_add(self, rhs: $t) -> $t {
+                    <$t>::saturating_add(self, rhs)
+                }
+
+                fn saturating_sub(self, rhs: $t) -> $t {
+                    <$t>::saturating_sub(self, rhs)
+                }
+
+                fn saturating_mul(self, rhs: $t) -> $t {
+                    <$t>::saturating_mul(self, rhs)
+                }
+
+                fn to_usize(self) -> Option<usize> {
+                    usize::try_from(self).ok()
+                }
+
+                fn from_usize(v: usize) -> Option<$t> {
+                    <$t>::try_from(v).ok()
+                }
+
+                fn to_u128(self) -> u128 {
+                    self as u128
+                }
+
+                fn from_u128_saturating(v: u128) -> $t {
+                    <$t>::try_from(v).unwrap_or(<$t>::MAX)
+                }
+            }
+        )*
+    };
+}
+
+impl_integer!(u32, u64, u128, usize);
+
+// Efficiency comparisons and linear bounds multiply two values together,
+// which can overflow even u128. The helpers below work on the full 256 bit
+// product so that they are exact for every supported integer type.
+
+/// Full 256 bit product of `a * b`, as (high, low) halves
+pub fn wide_mul(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+    let (a_high, a_low) = (a >> 64, a & MASK);
+    let (b_high, b_low) = (b >> 64, b & MASK);
+
+    let low_low = a_low * b_low;
+    let high_low = a_high * b_low;
+    let low_high = a_low * b_high;
+    let high_high = a_high * b_high;
+
+    // Sum of three values under 2^64, can not overflow
+    let middle = (low_low >> 64) + (high_low & MASK) + (low_high & MASK);
+    let low = (low_low & MASK) | (middle << 64);
+    let high = high_high + (high_low >> 64) + (low_high >> 64) + (middle >> 64);
+    (high, low)
+}
+
+/// Exactly compare `a * b` with `c * d`
+pub fn cmp_products<T: Integer>(a: T, b: T, c: T, d: T) -> Ordering {
+    let (a, b, c, d) = (a.to_u128(), b.to_u128(), c.to_u128(), d.to_u128());
+    match (a.checked_mul(b), c.checked_mul(d)) {
+        (Some(left), Some(right)) => left.cmp(&right),
+        _ => wide_mul(a, b).cmp(&wide_mul(c, d)),
+    }
+}
+
+/// Quotient and remainder of `(a * b) / c`,
+/// None if the quotient does not fit in a u128
+pub fn mul_div_rem(a: u128, b: u128, c: u128) -> Option<(u128, u128)> {
+    if let Some(product) = a.checked_mul(b) {
+        return Some((product / c, product % c));
+    }
+
+    let (high, low) = wide_mul(a, b);
+    if high >= c {
+        return None;
+    }
+
+    // Long division one bit at a time, the remainder stays below c
+    // but may need a 129th bit while shifting, which `carry` tracks
+    let mut remainder = high;
+    let mut quotient = 0u128;
+    for i in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((low >> i) & 1);
+        quotient <<= 1;
+        if carry == 1 || remainder >= c {
+            remainder = remainder.wrapping_sub(c);
+            quotient |= 1;
+        }
+    }
+    Some((quotient, remainder))
+}
+
+/// `floor((a * b) / c)`, saturating at `T::MAX`
+pub fn mul_div_floor<T: Integer>(a: T, b: T, c: T) -> T {
+    match mul_div_rem(a.to_u128(), b.to_u128(), c.to_u128()) {
+        Some((quotient, _)) => T::from_u128_saturating(quotient),
+        None => T::MAX,
+    }
+}
+
+/// `ceil((a * b) / c)`, saturating at `T::MAX`
+pub fn mul_div_ceil<T: Integer>(a: T, b: T, c: T) -> T {
+    match mul_div_rem(a.to_u128(), b.to_u128(), c.to_u128()) {
+        Some((quotient, 0)) => T::from_u128_saturating(quotient),
+        Some((quotient, _)) => T::from_u128_saturating(quotient).saturating_add(T::ONE),
+        None => T::MAX,
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn wide_mul_matches_narrow() {
+        assert_eq!(wide_mul(0, u128::MAX), (0, 0));
+        assert_eq!(wide_mul(1 << 64, 1 << 64), (1, 0));
+        assert_eq!(wide_mul(u128::MAX, 2), (1, u128::MAX - 1));
+        // (2^128 - 1)^2 = 2^256 - 2^129 + 1
+        assert_eq!(wide_mul(u128::MAX, u128::MAX), (u128::MAX - 1, 1));
+    }
+
+    #[test]
+    fn compare_products() {
+        // f32 sees 16777217 / 16777216 and 1 as the same
+        assert_eq!(cmp_products(16777217u64, 1, 1, 16777216), Ordering::Greater);
+        let big = u128::MAX / 3;
+        assert_eq!(cmp_products(big, big + 1, big + 1, big), Ordering::Equal);
+        assert_eq!(
+            cmp_products(big, big + 1, big - 1, big + 2),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn mul_div_wide() {
+        let big = u128::MAX / 3;
+        assert_eq!(mul_div_rem(big, 6, 3), Some((2 * big, 0)));
+        assert_eq!(mul_div_rem(big, 7, 1), None);
+        assert_eq!(mul_div_floor(u128::MAX, u128::MAX, u128::MAX), u128::MAX);
+        assert_eq!(mul_div_floor(u128::MAX, 10, u128::MAX / 2), 20);
+        assert_eq!(mul_div_ceil(u128::MAX, 10, u128::MAX / 2), 21);
+        assert_eq!(mul_div_floor(7u32, 3, 2), 10);
+        assert_eq!(mul_div_ceil(7u32, 3, 2), 11);
+        assert_eq!(mul_div_ceil(u32::MAX, 3, 2), u32::MAX);
+    }
+}
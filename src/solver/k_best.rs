@@ -0,0 +1,268 @@
+// The K best solutions of a knapsack problem, the most valuable first
+//
+// Minknap can not simply keep K incumbents, as its dominance checks drop every state that
+// another matches in value for no more weight, and the runners up are among those. Instead
+// the solutions are split up the way Lawler and Murty enumerate ranked solutions: once the
+// best solution of a set of solutions is found, the rest of the set is split into parts that
+// each fix one more free item against that solution. With the free items f1, f2, ... of the
+// best solution x, part i fixes f1 to f(i-1) as x has them and flips fi. The parts together
+// hold every other solution of the set exactly once.
+//
+// Each part's best solution is found by minknap, with its fixings, and the parts wait in
+// a priority queue ordered by the value of their best solution. Taking the K best from the
+// queue takes at most one minknap solve per item for each solution found.
+//
+// Every optimal solution is found the same way, only queueing the parts whose best solution
+// is worth the optimum. Ties are found without changing minknap, which keeps just one of
+// the states worth the same.
+
+use crate::solver::integer::*;
+use crate::solver::minknap;
+use crate::solver::problem::*;
+use crate::solver::registry::*;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// A set of solutions, given by its fixings, and the best solution in it
+struct Part<T> {
+    fixings: Vec<Fixing>,
+    solution: Solution<T>,
+    /// Parts worth the same are taken in the order they were found
+    sequence: usize,
+}
+
+impl<T: Integer> PartialEq for Part<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T: Integer> Eq for Part<T> {}
+
+impl<T: Integer> PartialOrd for Part<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Integer> Ord for Part<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.solution
+            .value
+            .cmp(&other.solution.value)
+            .then(other.sequence.cmp(&self.sequence))
+    }
+}
+
+struct Instance<'a, T> {
+    problem: &'a Problem<T>,
+    queue: BinaryHeap<Part<T>>,
+    parts_solved: usize,
+    /// Parts whose best solution is worth less are not queued
+    min_value: T,
+}
+
+impl<'a, T: Integer> Instance<'a, T> {
+    /// Queue the part with these fixings, if any solution fits it
+    fn push(&mut self, fixings: Vec<Fixing>) -> Result<(), SolveError> {
+        let mut part = self.problem.clone();
+        part.fixings = Some(fixings);
+        let solution = match minknap::solve(&part) {
+            Ok(solution) => solution,
+            Err(SolveError::Infeasible(_)) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        if solution.value < self.min_value {
+            return Ok(());
+        }
+        self.queue.push(Part {
+            fixings: part.fixings.unwrap(),
+            solution,
+            sequence: self.parts_solved,
+        });
+        self.parts_solved += 1;
+        Ok(())
+    }
+
+    /// Queue the parts holding every solution of `part` but its best
+    fn split(&mut self, part: &Part<T>) -> Result<(), SolveError> {
+        let mut fixings = part.fixings.clone();
+        for index in 0..fixings.len() {
+            if fixings[index] != Fixing::Free {
+                continue;
+            }
+            let (kept, flipped) = if part.solution.decision[index] == T::ZERO {
+                (Fixing::Out, Fixing::In)
+            } else {
+                (Fixing::In, Fixing::Out)
+            };
+            fixings[index] = flipped;
+            self.push(fixings.clone())?;
+            fixings[index] = kept;
+        }
+        Ok(())
+    }
+}
+
+/// Up to `k` distinct solutions, the most valuable first, such that no solution left out
+/// is worth more than the last one returned. Solutions worth the same are in no set order.
+/// Fewer are returned only if fewer solutions fit
+pub fn k_best<T: Integer>(problem: &Problem<T>, k: usize) -> Result<Vec<Solution<T>>, SolveError> {
+    ranked(problem, k, false)
+}
+
+/// Every optimal solution, up to `limit` of them, and whether more were left out.
+/// The optimum is unique if exactly one solution is returned and none were left out
+pub fn optimal_solutions<T: Integer>(
+    problem: &Problem<T>,
+    limit: usize,
+) -> Result<(Vec<Solution<T>>, bool), SolveError> {
+    // One more than the limit tells whether any were left out
+    let mut solutions = ranked(problem, limit.saturating_add(1), true)?;
+    let more = solutions.len() > limit;
+    solutions.truncate(limit);
+    Ok((solutions, more))
+}
+
+/// Up to `k` solutions, the most valuable first, only those worth the optimum if `optimal_only`
+fn ranked<T: Integer>(
+    problem: &Problem<T>,
+    k: usize,
+    optimal_only: bool,
+) -> Result<Vec<Solution<T>>, SolveError> {
+    problem.check_supported("k_best", &[])?;
+    problem.check_overflow()?;
+    let fixings = problem
+        .fixings
+        .clone()
+        .unwrap_or_else(|| vec![Fixing::Free; problem.items.len()]);
+
+    let mut instance = Instance {
+        problem,
+        queue: BinaryHeap::new(),
+        parts_solved: 0,
+        min_value: T::ZERO,
+    };
+    let mut solutions = Vec::new();
+    if k == 0 {
+        return Ok(solutions);
+    }
+    instance.push(fixings)?;
+    if optimal_only {
+        if let Some(best) = instance.queue.peek() {
+            instance.min_value = best.solution.value;
+        }
+    }
+    while let Some(part) = instance.queue.pop() {
+        // The last solution needs no split, as nothing is taken after it
+        if solutions.len() + 1 < k {
+            instance.split(&part)?;
+        }
+        solutions.push(part.solution);
+        if solutions.len() == k {
+            break;
+        }
+    }
+    Ok(solutions)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::solver::test_util::{subsets, Lcg};
+
+    fn example_problem(item_count: usize, seed: u64) -> Problem<u64> {
+        let items = Lcg::new(seed).items(item_count, 0..40, 1..26);
+        Problem::new(items, 4 * item_count as u64)
+    }
+
+    /// Value of every feasible solution, most valuable first
+    fn brute_force(problem: &Problem<u64>) -> Vec<u64> {
+        let mut values: Vec<u64> = subsets(problem)
+            .filter(|(taken, _, weight)| {
+                let fits_fixings =
+                    taken
+                        .iter()
+                        .enumerate()
+                        .all(|(i, &taken)| match problem.fixing(i) {
+                            Fixing::Free => true,
+                            Fixing::In => taken,
+                            Fixing::Out => !taken,
+                        });
+                fits_fixings && *weight <= problem.capacity
+            })
+            .map(|(_, value, _)| value)
+            .collect();
+        values.sort_unstable_by(|a, b| b.cmp(a));
+        values
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        for seed in 0..10 {
+            let mut problem = example_problem(10, seed);
+            if seed % 2 == 1 {
+                problem.fix(seed as usize % 10, Fixing::In);
+                problem.fix(3, Fixing::Out);
+            }
+            let all = brute_force(&problem);
+            let solutions = k_best(&problem, 25).unwrap();
+            let values: Vec<u64> = solutions.iter().map(|s| s.value).collect();
+            assert_eq!(values, all[..all.len().min(25)], "seed {}", seed);
+
+            for (rank, solution) in solutions.iter().enumerate() {
+                assert!(solution.validate(&problem));
+                assert!(solutions[..rank]
+                    .iter()
+                    .all(|other| other.decision != solution.decision));
+            }
+        }
+    }
+
+    #[test]
+    fn every_tied_optimum() {
+        let mut most_tied = 0;
+        for seed in 0..10 {
+            // Few distinct values make ties likely
+            let mut problem = example_problem(12, seed);
+            for item in problem.items.iter_mut() {
+                item.value = item.value % 4 + 1;
+                item.weight = item.weight % 5 + 1;
+            }
+            problem.capacity = 15;
+            let all = brute_force(&problem);
+            let tied = all.iter().filter(|&&value| value == all[0]).count();
+            most_tied = most_tied.max(tied);
+
+            let (solutions, more) = optimal_solutions(&problem, 1000).unwrap();
+            assert_eq!(solutions.len(), tied, "seed {}", seed);
+            assert!(!more);
+            for (rank, solution) in solutions.iter().enumerate() {
+                assert!(solution.validate(&problem));
+                assert_eq!(solution.value, all[0]);
+                assert!(solutions[..rank]
+                    .iter()
+                    .all(|other| other.decision != solution.decision));
+            }
+
+            let (capped, more) = optimal_solutions(&problem, 1).unwrap();
+            assert_eq!(capped.len(), 1);
+            assert_eq!(more, tied > 1);
+        }
+        assert!(most_tied > 1);
+    }
+
+    #[test]
+    fn fewer_solutions_than_asked_for() {
+        let items = vec![
+            Item::new(0, 5u32, 3),
+            Item::new(1, 4, 3),
+            Item::new(2, 9, 8),
+        ];
+        let problem = Problem::new(items, 6);
+        let solutions = k_best(&problem, 10).unwrap();
+        let values: Vec<u32> = solutions.iter().map(|s| s.value).collect();
+        assert_eq!(values, vec![9, 5, 4, 0]);
+        assert!(k_best(&problem, 0).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,564 @@
+// Multiple-choice knapsack problem, where items belong to classes
+// and exactly one item of each class is chosen
+//
+// This follows the ideas of Pisinger's minimal algorithm for the problem:
+//
+// Within a class, an item that weighs at least as much as another item while being worth
+// no more is dominated, and is never part of an optimal solution, so it is removed.
+// The remaining items, sorted by weight, form a curve of increasing value.
+// Items below the upper convex hull of that curve are LP-dominated, they are never used by
+// the linear relaxation, but may still be part of the integer optimum so they are kept.
+//
+// The linear relaxation starts from the lightest item of every class, and moves classes
+// up their hulls one step at a time, most efficient step first. The first step that does
+// not fit is the break step, and the integer solution just before it the break solution.
+//
+// Like minknap, states start from the break solution and an expanding core of classes is
+// enumerated around the break step. Steps are visited outwards from the break step in
+// efficiency order, and the class of each step is added to the core the first time one of
+// its steps is seen. Classes outside the core stay at their break choice. Every state is
+// bounded by linearly moving outside classes along the next steps in either direction.
+// Items without a class are treated as a class of their own, alongside taking nothing.
+
+use crate::converter::*;
+use crate::solver::integer::*;
+use crate::solver::problem::*;
+use crate::solver::registry::*;
+use std::collections::BTreeMap;
+use std::mem::size_of;
+
+/// Marks the end of a chain of choices
+const NO_CHOICE: usize = usize::MAX;
+
+/// One of the items a class can choose
+#[derive(Debug, Copy, Clone)]
+struct ClassItem<T> {
+    /// Index of the item in the problem, None for the option of taking nothing
+    index: Option<usize>,
+    value: T,
+    weight: T,
+}
+
+#[derive(Debug)]
+struct Class<T> {
+    /// Undominated items, by increasing weight and value
+    items: Vec<ClassItem<T>>,
+    /// Positions in `items` of the upper convex hull, the items not LP-dominated
+    hull: Vec<usize>,
+    /// Position in `items` of the item chosen by the break solution
+    break_choice: usize,
+}
+
+/// Moving a class from one hull item to the next
+#[derive(Debug)]
+struct Step<T> {
+    class: usize,
+    /// Position in `items` of the heavier item
+    to: usize,
+    value: T,
+    weight: T,
+}
+
+/// Group items by class, with each unclassed item in a class of its own
+fn collect_classes<T: Integer>(problem: &Problem<T>) -> Vec<Vec<ClassItem<T>>> {
+    let mut classes: BTreeMap<usize, Vec<ClassItem<T>>> = BTreeMap::new();
+    let mut unclassed = Vec::new();
+    for (index, item) in problem.items.iter().enumerate() {
+        let class_item = ClassItem {
+            index: Some(index),
+            value: item.value,
+            weight: item.weight,
+        };
+        match item.class {
+            Some(class) => classes.entry(class).or_default().push(class_item),
+            None => {
+                let nothing = ClassItem {
+                    index: None,
+                    value: T::ZERO,
+                    weight: T::ZERO,
+                };
+                unclassed.push(vec![nothing, class_item]);
+            }
+        }
+    }
+    classes.into_values().chain(unclassed).collect()
+}
+
+/// Remove items that are too heavy or dominated, then find the upper convex hull
+fn reduce_class<T: Integer>(
+    mut items: Vec<ClassItem<T>>,
+    capacity: T,
+) -> (Vec<ClassItem<T>>, Vec<usize>) {
+    items.retain(|item| item.weight <= capacity);
+    // Lightest first, most valuable first among equal weights,
+    // so an item is dominated exactly when it is worth no more than the last item kept
+    items.sort_unstable_by(|a, b| a.weight.cmp(&b.weight).then(b.value.cmp(&a.value)));
+    let mut kept: Vec<ClassItem<T>> = Vec::with_capacity(items.len());
+    for item in items {
+        if kept.last().is_none_or(|last| item.value > last.value) {
+            kept.push(item);
+        }
+    }
+
+    // Weights and values now strictly increase, so every slope is positive.
+    // The middle of three items is LP-dominated when the slope into it is no steeper
+    // than the slope out of it, collinear items are dropped too so hull slopes strictly decrease
+    let mut hull: Vec<usize> = Vec::with_capacity(kept.len());
+    for (position, item) in kept.iter().enumerate() {
+        while hull.len() >= 2 {
+            let a = &kept[hull[hull.len() - 2]];
+            let b = &kept[hull[hull.len() - 1]];
+            let into_b = cmp_products(
+                b.value - a.value,
+                item.weight - b.weight,
+                item.value - b.value,
+                b.weight - a.weight,
+            );
+            if into_b == std::cmp::Ordering::Greater {
+                break;
+            }
+            hull.pop();
+        }
+        hull.push(position);
+    }
+    (kept, hull)
+}
+
+/// Steps along the hull of every class, most efficient first
+fn hull_steps<T: Integer>(classes: &[Class<T>]) -> Vec<Step<T>> {
+    let mut steps = Vec::new();
+    for (class_index, class) in classes.iter().enumerate() {
+        for pair in class.hull.windows(2) {
+            let (from, to) = (&class.items[pair[0]], &class.items[pair[1]]);
+            steps.push(Step {
+                class: class_index,
+                to: pair[1],
+                value: to.value - from.value,
+                weight: to.weight - from.weight,
+            });
+        }
+    }
+    // Steps of one class strictly decrease in efficiency,
+    // so ties between classes are the only order the sort needs to settle
+    steps.sort_unstable_by(|a, b| {
+        cmp_products(b.value, a.weight, a.value, b.weight)
+            .then(a.class.cmp(&b.class))
+            .then(a.to.cmp(&b.to))
+    });
+    steps
+}
+
+/// A link in the chain of choices made for the classes in the core
+#[derive(Debug, Copy, Clone)]
+struct Choice {
+    parent: usize,
+    class: usize,
+    position: usize,
+}
+
+/// Value and weight of the items chosen for the classes in the core
+#[derive(Debug, Copy, Clone)]
+struct State<T> {
+    w: T,
+    p: T,
+    choice: usize,
+}
+
+pub struct Instance<'a, T: Integer> {
+    problem: &'a Problem<T>,
+    classes: Vec<Class<T>>,
+    steps: Vec<Step<T>>,
+    in_core: Vec<bool>,
+    /// Index of the break step, or the step count if every step fits
+    break_step: usize,
+    /// Lowest and highest step index visited so far
+    s: usize,
+    t: usize,
+    /// Value and weight of the break choices of the classes outside the core
+    rest_profit: T,
+    rest_weight: T,
+    lower_bound: T,
+    best_weight: T,
+    best_choice: usize,
+    choices: Vec<Choice>,
+    states_explored: usize,
+    bytes_used: usize,
+    verbose: bool,
+}
+
+impl<'a, T: Integer> Instance<'a, T> {
+    fn new(problem: &'a Problem<T>, verbose: bool) -> Result<Instance<'a, T>, SolveError> {
+        let mut classes = Vec::new();
+        let mut min_weight = T::ZERO;
+        for items in collect_classes(problem) {
+            // Items without a class can always be left out, so only a class can run out of items
+            let class = items[0].index.and_then(|index| problem.items[index].class);
+            let (items, hull) = reduce_class(items, problem.capacity);
+            match (items.first(), class) {
+                (Some(lightest), _) => min_weight += lightest.weight,
+                (None, Some(class)) => {
+                    return Err(SolveError::Infeasible(format!(
+                        "every item of class {} is heavier than the capacity {}",
+                        class, problem.capacity
+                    )))
+                }
+                (None, None) => unreachable!("an item without a class can be left out"),
+            }
+            classes.push(Class {
+                items,
+                hull,
+                break_choice: 0,
+            });
+        }
+        if min_weight > problem.capacity {
+            return Err(SolveError::Infeasible(format!(
+                "the lightest item of each class weighs {} together, over the capacity {}",
+                min_weight, problem.capacity
+            )));
+        }
+
+        // Take steps while they fit, the lightest items are always taken
+        let steps = hull_steps(&classes);
+        let mut profit: T = classes.iter().map(|c| c.items[0].value).sum();
+        let mut weight = min_weight;
+        let mut break_step = steps.len();
+        for (index, step) in steps.iter().enumerate() {
+            if weight + step.weight > problem.capacity {
+                break_step = index;
+                break;
+            }
+            profit += step.value;
+            weight += step.weight;
+            classes[step.class].break_choice = step.to;
+        }
+
+        let class_count = classes.len();
+        let bytes_used = size_of::<Instance<T>>()
+            + classes
+                .iter()
+                .map(|c| {
+                    c.items.capacity() * size_of::<ClassItem<T>>()
+                        + c.hull.capacity() * size_of::<usize>()
+                })
+                .sum::<usize>()
+            + steps.capacity() * size_of::<Step<T>>();
+
+        Ok(Instance {
+            problem,
+            classes,
+            steps,
+            in_core: vec![false; class_count],
+            break_step,
+            s: break_step,
+            t: break_step,
+            rest_profit: profit,
+            rest_weight: weight,
+            lower_bound: profit,
+            best_weight: weight,
+            best_choice: NO_CHOICE,
+            choices: Vec::new(),
+            states_explored: 0,
+            bytes_used,
+            verbose,
+        })
+    }
+
+    // Linear relaxation over the classes outside the core.
+    // Every step forward from t is at most as efficient as step t + 1,
+    // and every step back from s is at least as efficient as step s - 1
+    fn upper_bound(&self, profit: T, weight: T) -> T {
+        let capacity = self.problem.capacity;
+        if weight <= capacity {
+            match self.steps.get(self.t.wrapping_add(1)) {
+                Some(next) => {
+                    let linear_diff = mul_div_floor(capacity - weight, next.value, next.weight);
+                    profit.saturating_add(linear_diff)
+                }
+                None => profit,
+            }
+        } else if self.s > 0 {
+            let next = &self.steps[self.s - 1];
+            let linear_diff = mul_div_ceil(weight - capacity, next.value, next.weight);
+            profit.saturating_sub(linear_diff)
+        } else {
+            // Every class outside the core is already at its lightest item,
+            // so the state can never fit
+            T::ZERO
+        }
+    }
+
+    /// Enumerate every item of a class for every state, keeping undominated states
+    fn add_class(&mut self, class: usize, current_states: &[State<T>]) -> Vec<State<T>> {
+        self.in_core[class] = true;
+        let break_item = self.classes[class].items[self.classes[class].break_choice];
+        self.rest_profit -= break_item.value;
+        self.rest_weight -= break_item.weight;
+
+        let items = &self.classes[class].items;
+        let mut candidates = Vec::with_capacity(current_states.len() * items.len());
+        for state in current_states {
+            for (position, item) in items.iter().enumerate() {
+                candidates.push((
+                    state.w + item.weight,
+                    state.p + item.value,
+                    state.choice,
+                    position,
+                ));
+            }
+        }
+        // Lightest first, most valuable first among equal weights, as in reduce_class
+        candidates.sort_unstable_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+
+        let mut next_states: Vec<State<T>> = Vec::new();
+        for (w, p, parent, position) in candidates {
+            if next_states.last().is_some_and(|last| p <= last.p) {
+                continue;
+            }
+
+            let total_weight = w + self.rest_weight;
+            let total_profit = p + self.rest_profit;
+            if self.upper_bound(total_profit, total_weight) <= self.lower_bound {
+                continue;
+            }
+
+            self.choices.push(Choice {
+                parent,
+                class,
+                position,
+            });
+            let choice = self.choices.len() - 1;
+            if total_weight <= self.problem.capacity && total_profit > self.lower_bound {
+                self.lower_bound = total_profit;
+                self.best_weight = total_weight;
+                self.best_choice = choice;
+            }
+            next_states.push(State { w, p, choice });
+        }
+        self.states_explored += next_states.len();
+        next_states
+    }
+
+    fn core_size(&self) -> usize {
+        self.in_core.iter().filter(|&&c| c).count()
+    }
+
+    fn print_final_update(&mut self, current_states: &[State<T>]) {
+        self.bytes_used +=
+            std::mem::size_of_val(current_states) + self.choices.capacity() * size_of::<Choice>();
+        if !self.verbose {
+            return;
+        }
+        let core_percentage = 100.0 * (self.core_size() as f32 / self.classes.len() as f32);
+        println!(
+            "classes: {}, states_explored: {}, core_size: %{:.4}, mem_used: {} ({} bytes)",
+            self.classes.len(),
+            self.states_explored,
+            core_percentage,
+            human_readable_bytes(self.bytes_used),
+            self.bytes_used,
+        );
+    }
+
+    fn solve(&mut self) {
+        // Every step fits, so the break solution is the linear optimum
+        if self.break_step == self.steps.len() {
+            self.print_final_update(&[]);
+            return;
+        }
+
+        let start = State {
+            w: T::ZERO,
+            p: T::ZERO,
+            choice: NO_CHOICE,
+        };
+        let break_class = self.steps[self.break_step].class;
+        let mut current_states = self.add_class(break_class, &[start]);
+
+        while !current_states.is_empty() && (self.t + 1 < self.steps.len() || self.s > 0) {
+            if self.t + 1 < self.steps.len() {
+                self.t += 1;
+                let class = self.steps[self.t].class;
+                if !self.in_core[class] {
+                    current_states = self.add_class(class, &current_states);
+                }
+            }
+
+            if self.s > 0 {
+                self.s -= 1;
+                let class = self.steps[self.s].class;
+                if !self.in_core[class] {
+                    current_states = self.add_class(class, &current_states);
+                }
+            }
+        }
+        self.print_final_update(&current_states);
+    }
+
+    /// Break choices, overridden by the chain of choices leading to the best state
+    fn decision(&self) -> Vec<T> {
+        let mut positions: Vec<usize> = self.classes.iter().map(|c| c.break_choice).collect();
+        let mut choice = self.best_choice;
+        while choice != NO_CHOICE {
+            let link = self.choices[choice];
+            positions[link.class] = link.position;
+            choice = link.parent;
+        }
+
+        let mut decision = vec![T::ZERO; self.problem.items.len()];
+        for (class, position) in self.classes.iter().zip(positions) {
+            if let Some(index) = class.items[position].index {
+                decision[index] = T::ONE;
+            }
+        }
+        decision
+    }
+}
+
+/// Pisinger style expanding core solver for the multiple-choice knapsack problem
+pub struct Mcknap;
+
+impl<T: Integer> KnapsackSolver<T> for Mcknap {
+    fn name(&self) -> &'static str {
+        "Mcknap"
+    }
+
+    fn solve(
+        &self,
+        problem: &Problem<T>,
+        config: &SolveConfig,
+    ) -> Result<SolveReport<T>, SolveError> {
+        problem.check_supported("Mcknap", &[Extension::Classes])?;
+        if problem.fixings.is_some() {
+            return solve_fixed(self, problem, config);
+        }
+        problem.check_overflow()?;
+        let mut instance = Instance::new(problem, config.verbose)?;
+        instance.solve();
+        Ok(SolveReport {
+            solution: Solution {
+                decision: instance.decision(),
+                value: instance.lower_bound,
+                weight: instance.best_weight,
+                bins: None,
+            },
+            states_explored: Some(instance.states_explored),
+            bytes_used: Some(instance.bytes_used),
+        })
+    }
+}
+
+pub fn solve<T: Integer>(problem: &Problem<T>) -> Result<Solution<T>, SolveError> {
+    let report = Mcknap.solve(problem, &SolveConfig::default())?;
+    Ok(report.solution)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    fn classed_item(id: usize, value: u64, weight: u64, class: usize) -> Item<u64> {
+        let mut item = Item::new(id, value, weight);
+        item.class = Some(class);
+        item
+    }
+
+    /// Best value over every way of picking one item per class, or None if nothing fits
+    fn brute_force(problem: &Problem<u64>) -> Option<u64> {
+        let classes = collect_classes(problem);
+        let mut best = None;
+        let mut picks = vec![0; classes.len()];
+        loop {
+            let chosen = classes.iter().zip(&picks).map(|(c, &p)| c[p]);
+            let weight: u64 = chosen.clone().map(|i| i.weight).sum();
+            let value: u64 = chosen.map(|i| i.value).sum();
+            if weight <= problem.capacity && best.is_none_or(|b| value > b) {
+                best = Some(value);
+            }
+
+            // Advance the picks like an odometer
+            let mut class = 0;
+            loop {
+                if class == classes.len() {
+                    return best;
+                }
+                picks[class] += 1;
+                if picks[class] < classes[class].len() {
+                    break;
+                }
+                picks[class] = 0;
+                class += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn dominated_items_removed() {
+        let items = [(5, 2), (4, 3), (9, 4), (10, 6), (16, 8), (3, 20)]
+            .iter()
+            .enumerate()
+            .map(|(i, &(value, weight))| ClassItem {
+                index: Some(i),
+                value,
+                weight,
+            })
+            .collect();
+        let (kept, hull) = reduce_class(items, 10u64);
+        // Item 1 is dominated by item 0, and item 5 does not fit
+        let kept_indices: Vec<usize> = kept.iter().map(|i| i.index.unwrap()).collect();
+        assert_eq!(kept_indices, vec![0, 2, 3, 4]);
+        // Item 3 lies below the line between items 2 and 4
+        assert_eq!(hull, vec![0, 1, 3]);
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let mut items = Vec::new();
+        let mut id = 0;
+        for class in 0..5 {
+            for k in 0..4u64 {
+                let weight = (7 * id as u64 + 3 * class as u64) % 11 + k;
+                let value = (5 * id as u64 + 2 * k * k) % 17 + weight;
+                items.push(classed_item(id, value, weight, class));
+                id += 1;
+            }
+        }
+        items.push(Item::new(id, 9, 4));
+        items.push(Item::new(id + 1, 3, 6));
+
+        for capacity in [12, 17, 25, 31, 40, 60, 100] {
+            let problem = Problem::new(items.clone(), capacity);
+            let solution = solve(&problem).unwrap();
+            assert!(solution.validate(&problem));
+            assert_eq!(
+                Some(solution.value),
+                brute_force(&problem),
+                "capacity {}",
+                capacity
+            );
+        }
+    }
+
+    #[test]
+    fn infeasible_problems() {
+        let items = vec![
+            classed_item(0, 5, 6, 0),
+            classed_item(1, 7, 8, 0),
+            classed_item(2, 5, 5, 1),
+        ];
+        let problem = Problem::new(items.clone(), 10);
+        assert!(matches!(solve(&problem), Err(SolveError::Infeasible(_))));
+
+        let problem = Problem::new(items, 11);
+        let solution = solve(&problem).unwrap();
+        assert!(solution.validate(&problem));
+        assert_eq!(solution.value, 10);
+
+        let problem = Problem::new(problem.items, 5);
+        match solve(&problem) {
+            Err(SolveError::Infeasible(reason)) => {
+                assert!(reason.contains("class 0"), "{}", reason)
+            }
+            other => panic!("expected class 0 to be too heavy, got {:?}", other),
+        }
+    }
+}
@@ -1,29 +1,89 @@
 use crate::converter::*;
+use crate::solver::integer::*;
 use crate::solver::problem::*;
+use crate::solver::registry::*;
 use crate::solver::sol_tree::*;
 use std::mem::size_of;
 
+/// An item's index in the problem, along with a copy of its value and weight
+/// so sorting and bounds do not need to look back at the problem
 #[derive(Debug)]
-struct ItemEfficiency {
-    index: usize,
-    efficiency: f32,
+pub(crate) struct ItemEfficiency<T> {
+    pub(crate) index: usize,
+    pub(crate) value: T,
+    pub(crate) weight: T,
 }
 
-fn efficiency_ordering(problem: &Problem) -> (Vec<ItemEfficiency>, Vec<bool>, usize) {
+/// Items left for the search once fixings and weightless items are decided
+pub(crate) struct EfficiencyOrdering<T> {
+    /// Free items that fit, most efficient first
+    pub(crate) items: Vec<ItemEfficiency<T>>,
+    /// Takes every item fixed in and every weightless item
+    pub(crate) decision: Vec<bool>,
+    /// Value of the items the decision takes
+    pub(crate) base_value: T,
+    /// Weight of the items the decision takes
+    pub(crate) base_weight: T,
+    /// Capacity left for the ordered items
+    pub(crate) capacity: T,
+}
+
+/// Order the items by efficiency, after taking every item fixed in and every
+/// weightless item, and dropping items fixed out or too heavy for the capacity left
+pub(crate) fn efficiency_ordering<T: Integer>(
+    problem: &Problem<T>,
+) -> Result<EfficiencyOrdering<T>, SolveError> {
+    ordering(problem, false)
+}
+
+/// Like `efficiency_ordering`, but keeping the items too heavy for the capacity left,
+/// as the linear relaxation can still take part of one
+pub(crate) fn relaxation_ordering<T: Integer>(
+    problem: &Problem<T>,
+) -> Result<EfficiencyOrdering<T>, SolveError> {
+    ordering(problem, true)
+}
+
+fn ordering<T: Integer>(
+    problem: &Problem<T>,
+    keep_heavy: bool,
+) -> Result<EfficiencyOrdering<T>, SolveError> {
     let problem_item_count = problem.items.len();
     let mut decision = vec![false; problem_item_count];
-    let mut base_value = 0;
-    let mut item_efficiencies: Vec<ItemEfficiency> = problem
+    let mut base_value = T::ZERO;
+    let mut base_weight = T::ZERO;
+
+    // Variable reduction
+    // Items fixed in are taken before anything else
+    for (index, item) in problem.items.iter().enumerate() {
+        if problem.fixing(index) == Fixing::In {
+            decision[index] = true;
+            base_value += item.value;
+            base_weight += item.weight;
+        }
+    }
+    if base_weight > problem.capacity {
+        return Err(SolveError::Infeasible(format!(
+            "the items fixed in weigh {}, over the capacity of {}",
+            base_weight, problem.capacity
+        )));
+    }
+    let capacity = problem.capacity - base_weight;
+
+    let mut item_efficiencies: Vec<ItemEfficiency<T>> = problem
         .items
         .iter()
         .enumerate()
         // Variable reduction
+        // Remove items the fixings already decide
+        .filter(|(index, _)| problem.fixing(*index) == Fixing::Free)
+        // Variable reduction
         // Remove items that are larger than the capacity
-        .filter(|(_, item)| item.weight <= problem.capacity)
+        .filter(|(_, item)| keep_heavy || item.weight <= capacity)
         // Variable reduction
         // Remove items that are zero weight
         .filter(|(index, item)| {
-            let check = item.weight != 0;
+            let check = item.weight != T::ZERO;
             if !check {
                 decision[*index] = true;
                 base_value += item.value;
@@ -31,64 +91,78 @@ fn efficiency_ordering(problem: &Problem) -> (Vec<ItemEfficiency>, Vec<bool>, us
             check
         })
         .map(|(index, item)| {
-            if item.weight == 0 {
+            if item.weight == T::ZERO {
                 panic!("Items with zero weight should have been removed");
             }
             ItemEfficiency {
                 index,
-                efficiency: item.value as f32 / item.weight as f32,
+                value: item.value,
+                weight: item.weight,
             }
         })
         .collect();
 
     // We want Highest ratio to lowest
     // Hence b cmp a
-    item_efficiencies.sort_unstable_by(|a, b| b.efficiency.partial_cmp(&a.efficiency).unwrap());
-
-    (item_efficiencies, decision, base_value)
+    // Ratios are compared exactly by cross multiplying, floats can mis-order
+    // items once weights get into the millions
+    item_efficiencies.sort_unstable_by(|a, b| cmp_products(b.value, a.weight, a.value, b.weight));
+
+    Ok(EfficiencyOrdering {
+        items: item_efficiencies,
+        decision,
+        base_value,
+        base_weight,
+        capacity,
+    })
 }
 
-struct BreakSolution {
-    break_item: usize,
-    profit: usize,
-    weight: usize,
-    linear_profit: usize,
+pub(crate) struct BreakSolution<T> {
+    /// Position in the efficiency ordering of the first item that does not fit,
+    /// the number of ordered items if they all fit
+    pub(crate) break_item: usize,
+    pub(crate) profit: T,
+    pub(crate) weight: T,
+    /// Dantzig bound, the profit plus the break item's share of the residual capacity
+    pub(crate) linear_profit: T,
 }
 
 /// Calculate the break solution and populate the initial decision vector
 /// Any decisions we make are modifications to the break decision vector
-fn break_solution(
-    problem: &Problem,
-    item_efficiencies: &[ItemEfficiency],
+pub(crate) fn break_solution<T: Integer>(
+    capacity: T,
+    item_efficiencies: &[ItemEfficiency<T>],
     decision: &mut [bool],
-) -> BreakSolution {
+) -> BreakSolution<T> {
     // This is the number of items in the reduced problem
     let item_count = item_efficiencies.len();
     let mut result = BreakSolution {
         break_item: 0,
-        profit: 0,
-        weight: 0,
-        linear_profit: 0,
+        profit: T::ZERO,
+        weight: T::ZERO,
+        linear_profit: T::ZERO,
     };
-    let mut profit_sum = 0;
-    let mut weight_sum = 0;
+    let mut profit_sum = T::ZERO;
+    let mut weight_sum = T::ZERO;
     let mut i = 0;
     while i < item_count {
-        let index = item_efficiencies[i].index;
-        let item = &problem.items[index];
-        if item.weight + weight_sum < problem.capacity {
+        let item = &item_efficiencies[i];
+        if item.weight + weight_sum < capacity {
             profit_sum += item.value;
             weight_sum += item.weight;
-            decision[index] = true;
+            decision[item.index] = true;
         } else {
             result.break_item = i;
             result.profit = profit_sum;
             result.weight = weight_sum;
 
-            let remaining_weight = problem.capacity - weight_sum;
-            let break_item_efficiency = item_efficiencies[i].efficiency;
-            result.linear_profit =
-                profit_sum + (remaining_weight as f32 * break_item_efficiency).ceil() as usize;
+            let remaining_weight = capacity - weight_sum;
+            let break_item = &item_efficiencies[i];
+            result.linear_profit = profit_sum.saturating_add(mul_div_floor(
+                remaining_weight,
+                break_item.value,
+                break_item.weight,
+            ));
             break;
         }
         i += 1;
@@ -104,76 +178,196 @@ fn break_solution(
     result
 }
 
+/// Linear relaxation of the items with `lambda` taken off every value, leaving out the items
+/// worth no more than `lambda`. Returns the relaxation rounded down, the number of items it
+/// takes whole, and whether it takes part of one more.
+/// The break item is found by repeatedly splitting the items around their median efficiency,
+/// so no sort is needed
+fn reduced_relaxation<T: Integer>(
+    capacity: T,
+    items: &[ItemEfficiency<T>],
+    lambda: T,
+    reduced: &mut Vec<(T, T)>,
+) -> (T, usize, bool) {
+    reduced.clear();
+    reduced.extend(
+        items
+            .iter()
+            .filter(|item| item.value > lambda)
+            .map(|item| (item.value - lambda, item.weight)),
+    );
+    let mut profit = T::ZERO;
+    let mut room = capacity;
+    let mut taken = 0;
+    let mut remaining = &mut reduced[..];
+    while !remaining.is_empty() {
+        let middle = remaining.len() / 2;
+        remaining.select_nth_unstable_by(middle, |a, b| cmp_products(b.0, a.1, a.0, b.1));
+        let (more_efficient, rest) = std::mem::take(&mut remaining).split_at_mut(middle);
+        let (value, weight) = more_efficient
+            .iter()
+            .fold((T::ZERO, T::ZERO), |(value, weight), &(v, w)| {
+                (value.saturating_add(v), weight.saturating_add(w))
+            });
+        if weight > room {
+            remaining = more_efficient;
+            continue;
+        }
+        profit = profit.saturating_add(value);
+        room -= weight;
+        taken += middle;
+
+        let (median_value, median_weight) = rest[0];
+        if median_weight > room {
+            let part = mul_div_floor(room, median_value, median_weight);
+            return (profit.saturating_add(part), taken, room > T::ZERO);
+        }
+        profit = profit.saturating_add(median_value);
+        room -= median_weight;
+        taken += 1;
+        remaining = &mut rest[1..];
+    }
+    (profit, taken, false)
+}
+
+/// Upper bound from the number of items that fit, much tighter than the Dantzig bound
+/// when values rise with weights.
+///
+/// No solution takes more than m items, m being how many of the lightest items fit. So for
+/// any lambda, every solution is worth at most lambda * m plus the linear relaxation with
+/// lambda taken off every value. The bound is convex in lambda, falling while the relaxation
+/// takes more than m items, so a binary search on lambda finds its minimum. On strongly
+/// correlated instances, where each value is the weight plus a constant, lambda at the
+/// constant gives the capacity plus m times the constant, which a full knapsack of m items
+/// reaches
+pub(crate) fn cardinality_bound<T: Integer>(capacity: T, items: &[ItemEfficiency<T>]) -> T {
+    let mut weights: Vec<T> = items.iter().map(|item| item.weight).collect();
+    weights.sort_unstable();
+    let mut weight_sum = T::ZERO;
+    let most_items = weights
+        .iter()
+        .take_while(|&&weight| {
+            weight_sum = weight_sum.saturating_add(weight);
+            weight_sum <= capacity
+        })
+        .count();
+    let m = T::from_usize(most_items).unwrap_or(T::MAX);
+
+    let mut reduced = Vec::with_capacity(items.len());
+    let mut best = T::MAX;
+    let mut low = T::ZERO;
+    let mut high = items.iter().map(|item| item.value).max().unwrap_or(T::ZERO);
+    while low <= high {
+        let lambda = low + (high - low) / (T::ONE + T::ONE);
+        let (relaxation, taken, part) = reduced_relaxation(capacity, items, lambda, &mut reduced);
+        best = best.min(lambda.saturating_mul(m).saturating_add(relaxation));
+        if taken > most_items || (taken == most_items && part) {
+            low = match lambda.checked_add(T::ONE) {
+                Some(low) => low,
+                None => break,
+            };
+        } else if taken < most_items && lambda > T::ZERO {
+            high = lambda - T::ONE;
+        } else {
+            break;
+        }
+    }
+    best
+}
+
 /// Utility function for add and remove item funtions
 /// only use when next_states is known to not be empty
-fn last_profit(next_state: &[State]) -> usize {
+fn last_profit<T: Integer>(next_state: &[State<T>]) -> T {
     next_state.last().unwrap().p
 }
 
 /// Utility function for add and remove item funtions
 /// only use when next_states is known to not be empty
-fn last_weight(next_state: &[State]) -> usize {
+fn last_weight<T: Integer>(next_state: &[State<T>]) -> T {
     next_state.last().unwrap().w
 }
 
 #[derive(Debug, Copy, Clone)]
-pub struct State {
-    w: usize,
-    p: usize,
+pub struct State<T> {
+    w: T,
+    p: T,
     sol: SolCrumb,
 }
 
 /// Utility type
 /// Wanted named arguments for lower_bound function
-/// Easy to mix up four usizes
-struct UBCheck {
+/// Easy to mix up four numbers
+struct UBCheck<T> {
     next_s: Option<usize>, // None if next s would be negative
     next_t: usize,
-    new_weight: usize,
-    new_profit: usize,
+    new_weight: T,
+    new_profit: T,
 }
 
 /// Most of the state needed for MinKnap function
 /// Notably, state buffers are not included to simplify
 /// ownership situation
-pub struct Instance<'a> {
-    best_sol_weight: usize,
+pub struct Instance<'a, T: Integer> {
+    best_sol_weight: T,
     best_sol_item: usize,
     best_sol_level: usize,
     best_sol: SolCrumb,
     sol_level: usize,
     decision: Vec<bool>,
     item_order: Vec<usize>,
-    item_efficiencies: Vec<ItemEfficiency>,
-    break_solution: BreakSolution,
-    problem: &'a Problem,
+    item_efficiencies: Vec<ItemEfficiency<T>>,
+    break_solution: BreakSolution<T>,
+    problem: &'a Problem<T>,
     s: usize,
     t: usize,
-    lower_bound: usize,
-    max_state_weight: usize,
+    lower_bound: T,
+    /// No solution is worth more, so a lower bound reaching it ends the search
+    upper_bound: T,
+    max_state_weight: T,
     last_log_update: std::time::Instant,
     bytes_used: usize,
     states_explored: usize,
-    base_value: usize,
+    base_value: T,
+    base_weight: T,
+    /// Capacity left for the ordered items, after the items fixed in
+    capacity: T,
+    verbose: bool,
 }
 
-impl<'a> Instance<'a> {
-    fn new(problem: &Problem) -> Instance {
-        let (item_efficiencies, mut decision, base_value) = efficiency_ordering(problem);
+impl<'a, T: Integer> Instance<'a, T> {
+    fn new(problem: &Problem<T>, verbose: bool) -> Result<Instance<'_, T>, SolveError> {
+        let EfficiencyOrdering {
+            items: item_efficiencies,
+            mut decision,
+            base_value,
+            base_weight,
+            capacity,
+        } = efficiency_ordering(problem)?;
         let n = item_efficiencies.len();
-        let break_solution = break_solution(problem, &item_efficiencies, &mut decision);
+        let break_solution = break_solution(capacity, &item_efficiencies, &mut decision);
         let lower_bound = break_solution.profit;
         let b = break_solution.break_item;
+        let upper_bound = if b < n {
+            break_solution
+                .linear_profit
+                .min(cardinality_bound(capacity, &item_efficiencies))
+        } else {
+            break_solution.profit
+        };
         let s = b;
-        let t = b - 1;
-        let max_state_weight = problem.capacity + break_solution.weight;
-        let bytes_used = size_of::<Instance>()
-            + size_of::<Problem>()
+        // The core starts out empty, with t one before s.
+        // When the very first item is the break item this wraps around,
+        // and wraps back to 0 when the first item is added
+        let t = b.wrapping_sub(1);
+        // Problem::check_overflow guarantees this, and every state sum, fits in T
+        let max_state_weight = capacity + break_solution.weight;
+        let bytes_used = size_of::<Instance<T>>()
+            + size_of::<Problem<T>>()
             + (decision.capacity() * size_of::<bool>())
-            + (item_efficiencies.capacity() * size_of::<ItemEfficiency>())
-            + (problem.items.capacity() * size_of::<Item>());
+            + (item_efficiencies.capacity() * size_of::<ItemEfficiency<T>>())
+            + (problem.items.capacity() * size_of::<Item<T>>());
 
-        Instance {
+        Ok(Instance {
             best_sol_weight: break_solution.weight,
             best_sol_level: 0,
             best_sol_item: 0,
@@ -187,23 +381,27 @@ impl<'a> Instance<'a> {
             s,
             t,
             lower_bound,
+            upper_bound,
             max_state_weight,
             last_log_update: std::time::Instant::now(),
             bytes_used,
             states_explored: 0,
             base_value,
-        }
+            base_weight,
+            capacity,
+            verbose,
+        })
     }
 
     fn item_count(&self) -> usize {
         self.item_efficiencies.len()
     }
 
-    fn problem_capacity(&self) -> usize {
-        self.problem.capacity
+    fn problem_capacity(&self) -> T {
+        self.capacity
     }
 
-    fn item(&self, ordered_index: usize) -> Item {
+    fn item(&self, ordered_index: usize) -> Item<T> {
         let index = self.item_efficiencies[ordered_index].index;
         self.problem.items[index]
     }
@@ -211,34 +409,35 @@ impl<'a> Instance<'a> {
     // We calculate the upper bound by relaxing the integer
     // decision constraint. The best we can do with linear decisions
     // is to add or remove some ammount of the next most efficient / in-efficient item
-    fn upper_bound(&self, u: UBCheck) -> usize {
+    //
+    // Profits are integers, so rounding the linear bound down is still a valid bound.
+    // Integer arithmetic keeps the bound exact, where floats could round it below
+    // the true optimum and prune the state that leads to it
+    fn upper_bound(&self, u: UBCheck<T>) -> T {
         let n = self.item_count();
         if u.new_weight <= self.problem_capacity() {
             // Under capacity
             if u.next_t < n {
                 // Best we could do is linear add next t item
-                let weight_remainder = (self.problem.capacity - u.new_weight) as f32;
-                let next_t_efficiency = self.item_efficiencies[u.next_t].efficiency;
-                u.new_profit + (weight_remainder * next_t_efficiency).ceil() as usize
+                let weight_remainder = self.capacity - u.new_weight;
+                let next_t = &self.item_efficiencies[u.next_t];
+                let linear_diff = mul_div_floor(weight_remainder, next_t.value, next_t.weight);
+                u.new_profit.saturating_add(linear_diff)
             } else {
                 // No more items to add, we're done
                 u.new_profit
             }
         } else {
             // Over capacity
-            if u.next_s.is_some() {
+            if let Some(next_s) = u.next_s {
                 // Best we could do is linear remove next s item
-                let weight_remainder = (u.new_weight - self.problem.capacity) as f32;
-                let next_s_efficiency = self.item_efficiencies[u.next_s.unwrap()].efficiency;
-                let linear_diff = (weight_remainder * next_s_efficiency).ceil() as usize;
-                if linear_diff > u.new_profit {
-                    0
-                } else {
-                    u.new_profit - linear_diff
-                }
+                let weight_remainder = u.new_weight - self.capacity;
+                let next_s = &self.item_efficiencies[next_s];
+                let linear_diff = mul_div_ceil(weight_remainder, next_s.value, next_s.weight);
+                u.new_profit.saturating_sub(linear_diff)
             } else {
-                // No more items to remove, we're done
-                u.new_profit
+                // No more items to remove, the state can never fit
+                T::ZERO
             }
         }
     }
@@ -253,7 +452,7 @@ impl<'a> Instance<'a> {
 
     /// When changing a decision from the break solution, we need to check
     /// for a new lower bound
-    fn check_for_new_lower_bound(&mut self, s: &State) {
+    fn check_for_new_lower_bound(&mut self, s: &State<T>) {
         if s.w <= self.problem_capacity() && s.p > self.lower_bound {
             self.lower_bound = s.p;
             self.best_sol = s.sol;
@@ -270,7 +469,7 @@ impl<'a> Instance<'a> {
     }
 
     /// Trying adding item at sorted index self.t to the core
-    fn add_item_t(&mut self, current_states: &[State], next_states: &mut Vec<State>) {
+    fn add_item_t(&mut self, current_states: &[State<T>], next_states: &mut Vec<State<T>>) {
         // For every state, we need to try both adding and not adding the item
         // However, we also need to maintain profit and weight ordering of states
         // Such that duplicates and dominated states can be discarded
@@ -324,7 +523,7 @@ impl<'a> Instance<'a> {
 
                 // Ensure this state passes bounds check
                 let upper_bound = self.upper_bound(UBCheck {
-                    next_s: Some(self.s),
+                    next_s: self.s.checked_sub(1),
                     next_t: self.t + 1,
                     new_profit: change_profit,
                     new_weight: change_weight,
@@ -370,7 +569,7 @@ impl<'a> Instance<'a> {
                 }
 
                 let upper_bound = self.upper_bound(UBCheck {
-                    next_s: Some(self.s),
+                    next_s: self.s.checked_sub(1),
                     next_t: self.t + 1,
                     new_profit: keep_state.p,
                     new_weight: keep_state.w,
@@ -394,7 +593,7 @@ impl<'a> Instance<'a> {
     }
 
     /// Trying removing item at sorted index self.s to the core
-    fn remove_item_s(&mut self, current_states: &[State], next_states: &mut Vec<State>) {
+    fn remove_item_s(&mut self, current_states: &[State<T>], next_states: &mut Vec<State<T>>) {
         // Similiar to add_item, see comments there
         self.add_to_item_order(self.s);
         let item = self.item(self.s);
@@ -408,7 +607,7 @@ impl<'a> Instance<'a> {
                 let keep_state = current_states[keep_index];
                 debug_assert!(keep_index < state_count);
 
-                debug_assert!(keep_state.w < self.max_state_weight);
+                debug_assert!(keep_state.w <= self.max_state_weight);
                 if keep_state.w > self.max_state_weight {
                     keep_index += 1;
                     continue;
@@ -421,7 +620,7 @@ impl<'a> Instance<'a> {
 
                 let upper_bound = self.upper_bound(UBCheck {
                     next_s: if self.s > 0 { Some(self.s - 1) } else { None },
-                    next_t: self.t,
+                    next_t: self.t.wrapping_add(1),
                     new_profit: keep_state.p,
                     new_weight: keep_state.w,
                 });
@@ -453,7 +652,7 @@ impl<'a> Instance<'a> {
                 let upper_bound = self.upper_bound(UBCheck {
                     next_s: if self.s > 0 { Some(self.s - 1) } else { None },
 
-                    next_t: self.t,
+                    next_t: self.t.wrapping_add(1),
                     new_profit: change_profit,
                     new_weight: change_weight,
                 });
@@ -483,8 +682,8 @@ impl<'a> Instance<'a> {
 
     fn swap_state_buffers(
         &mut self,
-        current_states: &mut Vec<State>,
-        next_states: &mut Vec<State>,
+        current_states: &mut Vec<State<T>>,
+        next_states: &mut Vec<State<T>>,
     ) {
         current_states.clear();
         std::mem::swap(current_states, next_states);
@@ -505,11 +704,12 @@ impl<'a> Instance<'a> {
 
     fn bytes_estimate(
         &mut self,
-        current_states: &Vec<State>,
-        next_states: &Vec<State>,
+        current_states: &Vec<State<T>>,
+        next_states: &Vec<State<T>>,
         sol_tree: &SolTree,
     ) -> usize {
-        let state_bytes = (current_states.capacity() + next_states.capacity()) * size_of::<State>();
+        let state_bytes =
+            (current_states.capacity() + next_states.capacity()) * size_of::<State<T>>();
         let sol_tree_bytes = sol_tree.bytes_used();
         let item_order_bytes = self.item_order.capacity() * size_of::<usize>();
         self.bytes_used + state_bytes + sol_tree_bytes + item_order_bytes
@@ -518,15 +718,18 @@ impl<'a> Instance<'a> {
     fn print_update(
         &mut self,
         i: usize,
-        current_states: &Vec<State>,
-        next_states: &Vec<State>,
+        current_states: &Vec<State<T>>,
+        next_states: &Vec<State<T>>,
         sol_tree: &SolTree,
     ) {
+        if !self.verbose {
+            return;
+        }
         let n = self.item_count();
         let elapsed_time = self.last_log_update.elapsed().as_millis();
-        if i != 0 && ((i < 10 || (i < 100 && i % 10 == 0)) || elapsed_time > 1500) {
+        if i != 0 && ((i < 10 || (i < 100 && i.is_multiple_of(10))) || elapsed_time > 1500) {
             self.last_log_update = std::time::Instant::now();
-            let core_width = (self.t - self.s) + 1;
+            let core_width = self.t.wrapping_sub(self.s).wrapping_add(1);
             let core_percentage = 100.0 * (core_width as f32 / n as f32);
             let bytes_estimate = self.bytes_estimate(current_states, next_states, sol_tree);
             let hr_bytes = human_readable_bytes(bytes_estimate);
@@ -544,14 +747,19 @@ impl<'a> Instance<'a> {
     fn print_final_update(
         &mut self,
         i: usize,
-        current_states: &Vec<State>,
-        next_states: &Vec<State>,
+        current_states: &Vec<State<T>>,
+        next_states: &Vec<State<T>>,
         sol_tree: &SolTree,
     ) {
+        // Keep the final estimate around for the solve report
+        self.bytes_used = self.bytes_estimate(current_states, next_states, sol_tree);
+        if !self.verbose {
+            return;
+        }
         let n = self.item_count();
-        let core_width = (self.t - self.s) + 1;
+        let core_width = self.t.wrapping_sub(self.s).wrapping_add(1);
         let core_percentage = 100.0 * (core_width as f32 / n as f32);
-        let bytes_estimate = self.bytes_estimate(current_states, next_states, sol_tree);
+        let bytes_estimate = self.bytes_used;
         let hr_bytes = human_readable_bytes(bytes_estimate);
         println!(
             "final i: {}, states_explored: {}, core_size: %{:.4}, mem_used: {} ({} bytes)",
@@ -559,7 +767,7 @@ impl<'a> Instance<'a> {
         );
     }
 
-    fn backup_solution_history(&mut self, sol_tree: &mut SolTree, current_states: &mut [State]) {
+    fn backup_solution_history(&mut self, sol_tree: &mut SolTree, current_states: &mut [State<T>]) {
         self.sol_level += 1;
         if self.sol_level >= 64 {
             self.sol_level = 0;
@@ -591,15 +799,18 @@ impl<'a> Instance<'a> {
         while !current_states.is_empty() && i < n {
             self.print_update(i, &current_states, &next_states, &sol_tree);
 
-            if self.t < n - 1 {
-                self.t += 1;
+            if self.t.wrapping_add(1) < n {
+                self.t = self.t.wrapping_add(1);
                 self.add_item_t(&current_states, &mut next_states);
                 self.swap_state_buffers(&mut current_states, &mut next_states);
                 self.backup_solution_history(&mut sol_tree, &mut current_states);
                 i += 1;
             }
 
-            if self.best_sol_weight == self.problem.capacity {
+            // Nothing can beat a lower bound that reaches the upper bound. Filling the
+            // capacity exactly is not enough, a full knapsack can still be worth less
+            // than another full knapsack
+            if self.lower_bound >= self.upper_bound {
                 break;
             }
 
@@ -611,7 +822,7 @@ impl<'a> Instance<'a> {
                 i += 1;
             }
 
-            if self.best_sol_weight == self.problem.capacity {
+            if self.lower_bound >= self.upper_bound {
                 break;
             }
         }
@@ -620,12 +831,84 @@ impl<'a> Instance<'a> {
     }
 }
 
-pub fn solve(problem: &Problem) -> Result<Solution, Box<dyn std::error::Error>> {
-    let mut instance = Instance::new(problem);
-    instance.solve();
-    Ok(Solution {
-        decision: instance.decision,
-        value: instance.lower_bound + instance.base_value,
-        weight: instance.best_sol_weight,
-    })
+/// Pisinger's minimal algorithm for the 0-1 knapsack problem
+pub struct Minknap;
+
+impl<T: Integer> KnapsackSolver<T> for Minknap {
+    fn name(&self) -> &'static str {
+        "Minknap"
+    }
+
+    fn solve(
+        &self,
+        problem: &Problem<T>,
+        config: &SolveConfig,
+    ) -> Result<SolveReport<T>, SolveError> {
+        problem.check_supported("Minknap", &[Extension::Covering])?;
+        if problem.covering {
+            return solve_covering(self, problem, config);
+        }
+        problem.check_overflow()?;
+        let mut instance = Instance::new(problem, config.verbose)?;
+        instance.solve();
+        Ok(SolveReport {
+            solution: Solution::from_bools(
+                &instance.decision,
+                instance.lower_bound + instance.base_value,
+                instance.best_sol_weight + instance.base_weight,
+            ),
+            states_explored: Some(instance.states_explored),
+            bytes_used: Some(instance.bytes_used),
+        })
+    }
+}
+
+pub fn solve<T: Integer>(problem: &Problem<T>) -> Result<Solution<T>, SolveError> {
+    let report = Minknap.solve(problem, &SolveConfig::default())?;
+    Ok(report.solution)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::solver::dynamic;
+
+    #[test]
+    fn full_knapsack_is_not_always_optimal() {
+        let items = [
+            (39, 1),
+            (19, 0),
+            (20, 23),
+            (23, 26),
+            (18, 4),
+            (10, 29),
+            (10, 3),
+        ]
+        .iter()
+        .enumerate()
+        .map(|(id, &(value, weight))| Item::new(id, value, weight))
+        .collect();
+        let problem = Problem::new(items, 31u64);
+        let solution = solve(&problem).unwrap();
+        assert!(solution.validate(&problem));
+        assert_eq!(solution.value, dynamic::solve(&problem).unwrap().value);
+    }
+
+    #[test]
+    fn cardinality_bound_is_tight_on_strongly_correlated_items() {
+        // Each value is the weight plus 10, and at most two items fit
+        let items = [3u64, 4, 5, 6]
+            .iter()
+            .enumerate()
+            .map(|(id, &weight)| Item::new(id, weight + 10, weight))
+            .collect();
+        let problem = Problem::new(items, 9);
+        let ordering = efficiency_ordering(&problem).unwrap();
+        let mut decision = ordering.decision.clone();
+        let break_solution = break_solution(ordering.capacity, &ordering.items, &mut decision);
+        assert_eq!(break_solution.linear_profit, 33);
+        // Two items filling the capacity are worth 9 + 2 * 10
+        assert_eq!(cardinality_bound(ordering.capacity, &ordering.items), 29);
+        assert_eq!(solve(&problem).unwrap().value, 29);
+    }
 }
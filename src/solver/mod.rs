@@ -1,25 +1,75 @@
-mod dynamic;
-mod greedy;
-mod minknap;
-mod problem;
+pub mod biobjective;
+pub mod bounded;
+pub mod cardinality;
+pub mod conflict;
+pub mod dynamic;
+pub mod expknap;
+pub mod fractional;
+pub mod greedy;
+pub mod integer;
+pub mod k_best;
+pub mod mcknap;
+pub mod minknap;
+pub mod multidim;
+pub mod multiple;
+pub mod parse;
+pub mod precedence;
+pub mod problem;
+pub mod profile;
+pub mod registry;
+pub mod robust;
+pub mod sensitivity;
 mod sol_tree;
+pub mod subset_sum;
+#[cfg(test)]
+mod test_util;
+pub mod unbounded;
 
+use crate::solver::integer::*;
+use crate::solver::parse::*;
 use crate::solver::problem::*;
+use crate::solver::registry::*;
 
 use clap::arg_enum;
 use clap::Parser;
 use std::fs::File;
 use std::io::BufReader;
+use std::io::Read;
 use std::path::PathBuf;
 use std::time::Instant;
 
 arg_enum! {
-/// The different solver implementations that are available
+/// The solver implementations available from the command line,
+/// each is looked up by name in the default `SolverRegistry`
 #[derive(Parser, Debug)]
 pub enum Solver {
     Greedy,
     Dynamic,
     Minknap,
+    Expknap,
+    Bounded,
+    Unbounded,
+    Mcknap,
+    Multidim,
+    Multiple,
+    MultipleExact,
+    SubsetSum,
+    Fractional,
+    Cardinality,
+    Conflict,
+    Precedence,
+    Biobjective,
+    Robust,
+}
+}
+
+arg_enum! {
+/// Integer type used for weights and values
+#[derive(Parser, Debug, Clone, Copy)]
+pub enum IntType {
+    U32,
+    U64,
+    U128,
 }
 }
 
@@ -29,6 +79,64 @@ pub struct Options {
     #[clap(short, long, default_value_t = Solver::Minknap)]
     solver: Solver,
 
+    /// Integer type for weights and values.
+    /// U32 halves solver memory, U128 allows larger sums.
+    /// Problems that would overflow the chosen type are solved with a wider one
+    #[clap(long, default_value_t = IntType::U64)]
+    int_type: IntType,
+
+    /// Solve as a covering problem, taking items that weigh at least the capacity
+    /// for the least total value, as a `covering` line in the problem file does
+    #[clap(long)]
+    covering: bool,
+
+    /// Ids of items every solution must take, alongside any `fix` lines in the problem file
+    #[clap(long, value_name = "IDS", value_delimiter = ',')]
+    fix_in: Vec<usize>,
+
+    /// Ids of items every solution must leave out
+    #[clap(long, value_name = "IDS", value_delimiter = ',')]
+    fix_out: Vec<usize>,
+
+    /// Print the optimal value for every capacity from LOW to HIGH as CSV, one row for
+    /// each capacity where it rises, instead of solving at the problem's capacity
+    #[clap(
+        long,
+        value_names = &["LOW", "HIGH"],
+        value_delimiter = ',',
+        number_of_values = 2
+    )]
+    profile: Vec<u128>,
+
+    /// Print the optimal value for each of the comma separated capacities as CSV
+    #[clap(
+        long,
+        value_name = "CAPACITIES",
+        value_delimiter = ',',
+        conflicts_with = "profile"
+    )]
+    profile_grid: Vec<u128>,
+
+    /// Add the decision vector to each row of the capacity profile
+    #[clap(long)]
+    profile_decisions: bool,
+
+    /// List the K most valuable solutions instead of only the best one
+    #[clap(long, value_name = "K")]
+    k_best: Option<usize>,
+
+    /// List every optimal solution, up to LIMIT of them, to tell whether the optimum is unique
+    #[clap(long, value_name = "LIMIT", conflicts_with = "k-best")]
+    all_optima: Option<usize>,
+
+    /// Report how far each item's value and weight, and the capacity, can change
+    /// while the solution stays optimal. Not available for the Biobjective solver
+    #[clap(
+        long,
+        conflicts_with_all = &["profile", "profile-grid", "k-best", "all-optima"]
+    )]
+    sensitivity: bool,
+
     /// Do no print the decision vector
     #[clap(short, long)]
     no_print_solution: bool,
@@ -39,25 +147,178 @@ pub struct Options {
     input_file: Option<PathBuf>,
 }
 
-pub fn run(options: &Options) -> Result<(), Box<dyn std::error::Error>> {
-    let problem;
+impl IntType {
+    fn wider(&self) -> Option<IntType> {
+        match self {
+            IntType::U32 => Some(IntType::U64),
+            IntType::U64 => Some(IntType::U128),
+            IntType::U128 => None,
+        }
+    }
+}
+
+fn input_name(options: &Options) -> String {
+    match &options.input_file {
+        Some(input_path) => input_path.display().to_string(),
+        None => "STD IN".to_string(),
+    }
+}
+
+/// Fix the items with the given ids, as `fix` lines in the problem file do
+fn fix_items<T: Integer>(
+    problem: &mut Problem<T>,
+    ids: &[usize],
+    fixing: Fixing,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for &id in ids {
+        let mut indices = problem.items.iter().enumerate().filter(|(_, i)| i.id == id);
+        match (indices.next(), indices.next()) {
+            (Some((index, _)), None) => problem.fix(index, fixing),
+            (Some(_), Some(_)) => {
+                return Err(format!("Can not fix item {}, several items have that id", id).into())
+            }
+            (None, _) => return Err(format!("Can not fix item {}, no item has that id", id).into()),
+        }
+    }
+    Ok(())
+}
+
+/// Solve with the chosen solver, except that subset-sum instances are handed from the
+/// default solver to the dedicated one. Minknap still solves those whose capacity is too
+/// large for the subset-sum bitset
+fn solve_routed<'a, T: Integer>(
+    registry: &'a SolverRegistry<T>,
+    solver: &Solver,
+    problem: &Problem<T>,
+    config: &SolveConfig,
+) -> Result<(&'a dyn KnapsackSolver<T>, SolveReport<T>), SolveError> {
+    let chosen = registry.get(&solver.to_string())?;
+    if let Solver::Minknap = solver {
+        if problem.is_subset_sum() {
+            println!("Every item is worth its weight, solving as a subset-sum problem");
+            let subset_sum = registry.get(&Solver::SubsetSum.to_string())?;
+            match subset_sum.solve(problem, config) {
+                Err(SolveError::TooLarge { .. }) => {
+                    println!("The capacity is too large for a subset-sum bitset, solving with Minknap instead");
+                }
+                result => return Ok((subset_sum, result?)),
+            }
+        }
+    }
+    let report = chosen.solve(problem, config)?;
+    Ok((chosen, report))
+}
+
+/// The whole input is read up front, so that it can be parsed again
+/// with a wider integer type if it does not fit in the requested one
+fn read_input(options: &Options) -> Result<String, Box<dyn std::error::Error>> {
+    let mut input = String::new();
     if let Some(input_path) = &options.input_file {
         let input_file = File::open(input_path)?;
-        let input_reader = BufReader::new(input_file);
-        problem = Problem::read(input_reader)?
+        BufReader::new(input_file).read_to_string(&mut input)?;
     } else {
-        let stdin = std::io::stdin();
-        let input_reader = BufReader::new(stdin);
-        problem = Problem::read(input_reader)?
+        BufReader::new(std::io::stdin()).read_to_string(&mut input)?;
+    }
+    Ok(input)
+}
+
+pub fn run(options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    // Clap can only check conflicts between arguments, not with the chosen solver
+    if let (true, Solver::Biobjective) = (options.sensitivity, &options.solver) {
+        return Err(
+            "--sensitivity can not be used with the Biobjective solver, \
+                    which lists a Pareto front rather than one optimal solution"
+                .into(),
+        );
+    }
+    let input = read_input(options)?;
+    let mut int_type = options.int_type;
+    loop {
+        let overflow = match int_type {
+            IntType::U32 => run_typed::<u32>(options, &input)?,
+            IntType::U64 => run_typed::<u64>(options, &input)?,
+            IntType::U128 => run_typed::<u128>(options, &input)?,
+        };
+
+        match (overflow, int_type.wider()) {
+            (None, _) => return Ok(()),
+            (Some(reason), Some(wider)) => {
+                println!(
+                    "Problem does not fit in {} ({}), widening to {}",
+                    int_type, reason, wider
+                );
+                int_type = wider;
+            }
+            (Some(reason), None) => {
+                let message = format!(
+                    "Problem from {} does not fit in {}, {}",
+                    input_name(options),
+                    int_type,
+                    reason
+                );
+                return Err(message.into());
+            }
+        }
+    }
+}
+
+/// Solve the problem using `T` for weights and values.
+/// If the problem, or the sums solvers need, do not fit in `T`
+/// nothing is solved and the reason is returned instead
+fn run_typed<T: Integer>(
+    options: &Options,
+    input: &str,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let mut problem = match Problem::<T>::read(input.as_bytes()) {
+        Ok(problem) => problem,
+        Err(e) if matches!(e.cause, ParseErrorCause::TooLarge { .. }) => {
+            return Ok(Some(e.to_string()))
+        }
+        Err(e) => {
+            let message = format!("Could not read problem from {}, {}", input_name(options), e);
+            return Err(message.into());
+        }
     };
 
+    // Plain problem files are solved as unbounded problems by the Unbounded solver
+    if let Solver::Unbounded = options.solver {
+        problem.make_unbounded();
+    }
+    if options.covering {
+        problem.covering = true;
+    }
+    fix_items(&mut problem, &options.fix_in, Fixing::In)?;
+    fix_items(&mut problem, &options.fix_out, Fixing::Out)?;
+
+    match problem.check_overflow() {
+        Err(e @ SolveError::Overflow(_)) => return Ok(Some(e.to_string())),
+        Err(e) => return Err(e.into()),
+        Ok(()) => {}
+    }
+
+    if !options.profile.is_empty() || !options.profile_grid.is_empty() {
+        return print_profile(options, &problem);
+    }
+    if let Some(k) = options.k_best {
+        print_k_best(options, &problem, k)?;
+        return Ok(None);
+    }
+    if let Some(limit) = options.all_optima {
+        print_optimal(options, &problem, limit)?;
+        return Ok(None);
+    }
+    if let Solver::Biobjective = options.solver {
+        print_front(options, &problem)?;
+        return Ok(None);
+    }
+
+    let registry = SolverRegistry::<T>::default();
+    let config = SolveConfig { verbose: true };
+
     let start_time = Instant::now();
 
-    let solution = match options.solver {
-        Solver::Greedy => greedy::solve(&problem),
-        Solver::Dynamic => dynamic::solve(&problem),
-        Solver::Minknap => minknap::solve(&problem)?,
-    };
+    let (solver, report) = solve_routed(&registry, &options.solver, &problem, &config)?;
+    let solution = report.solution;
 
     let solve_time = start_time.elapsed().as_millis() as f32 / 1000.0;
 
@@ -66,16 +327,264 @@ pub fn run(options: &Options) -> Result<(), Box<dyn std::error::Error>> {
     }
 
     if !options.no_print_solution {
-        println!("Id\tDecision\tGD");
-        for i in 0..problem.items.len() {
-            println!("{}\t{}", problem.items[i].id, solution.decision[i] as u8);
+        match &solution.bins {
+            Some(bins) => {
+                println!("Id\tDecision\tBin");
+                for ((item, decision), bin) in
+                    problem.items.iter().zip(&solution.decision).zip(bins)
+                {
+                    let bin = bin.map_or("-".to_string(), |b| b.to_string());
+                    println!("{}\t{}\t{}", item.id, decision, bin);
+                }
+            }
+            None => {
+                println!("Id\tDecision\tGD");
+                for i in 0..problem.items.len() {
+                    println!("{}\t{}", problem.items[i].id, solution.decision[i]);
+                }
+            }
         }
     }
 
+    // Every bin together, for multiple knapsack problems
+    let capacity: T = problem.bin_capacities().into_iter().sum();
+    let slack = if problem.covering {
+        format!("Excess Weight: {}", solution.weight - capacity)
+    } else {
+        format!("Unused Capacity: {}", capacity - solution.weight)
+    };
     println!(
-        "Solver Used: {:?}, Solution Value: {}, Solution Weight: {}, Target Capacity: {}, Unused Capacity: {}, Time Elapsed: {}",
-        options.solver, solution.value, solution.weight, problem.capacity, problem.capacity - solution.weight, solve_time,
+        "Solver Used: {}, Solution Value: {}, Solution Weight: {}, Target Capacity: {}, {}, Time Elapsed: {}",
+        solver.name(), solution.value, solution.weight, capacity, slack, solve_time,
     );
 
+    if options.sensitivity {
+        print_sensitivity(&problem, &solution)?;
+    }
+
+    Ok(None)
+}
+
+/// Print the ranges of each item's value and weight, and of the capacity,
+/// that the solution stays optimal over
+fn print_sensitivity<T: Integer>(
+    problem: &Problem<T>,
+    solution: &Solution<T>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let sensitivity = sensitivity::analyse(problem, solution)?;
+    println!("Id\tDecision\tValue Range\tWeight Range");
+    for (item, range) in problem.items.iter().zip(&sensitivity.items) {
+        println!(
+            "{}\t{}\t{}\t{}",
+            item.id,
+            u8::from(range.taken),
+            range.value,
+            range.weight
+        );
+    }
+    println!("Capacity Range: {}", sensitivity.capacity);
+    Ok(())
+}
+
+/// Print the capacity profile asked for by the options as CSV. As with solving,
+/// capacities that do not fit in `T` are returned as the reason to widen it
+fn print_profile<T: Integer>(
+    options: &Options,
+    problem: &Problem<T>,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let mut capacities = Vec::new();
+    for &capacity in options.profile.iter().chain(&options.profile_grid) {
+        let converted = T::from_u128_saturating(capacity);
+        if converted.to_u128() != capacity {
+            return Ok(Some(format!("capacity {} is too large", capacity)));
+        }
+        capacities.push(converted);
+    }
+
+    let steps = match options.profile.as_slice() {
+        [_, _] => profile::capacity_profile(
+            problem,
+            capacities[0],
+            capacities[1],
+            options.profile_decisions,
+        ),
+        _ => profile::capacity_grid(problem, &capacities, options.profile_decisions),
+    };
+    let steps = match steps {
+        Err(e @ SolveError::Overflow(_)) => return Ok(Some(e.to_string())),
+        result => result?,
+    };
+
+    if options.profile_decisions {
+        println!("capacity,value,weight,decision");
+    } else {
+        println!("capacity,value,weight");
+    }
+    for step in steps {
+        match &step.decision {
+            Some(decision) => {
+                let decision: String = decision.iter().map(|d| d.to_string()).collect();
+                println!(
+                    "{},{},{},{}",
+                    step.capacity, step.value, step.weight, decision
+                );
+            }
+            None => println!("{},{},{}", step.capacity, step.value, step.weight),
+        }
+    }
+    Ok(None)
+}
+
+/// Print the `k` most valuable solutions, one row each
+fn print_k_best<T: Integer>(
+    options: &Options,
+    problem: &Problem<T>,
+    k: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let start_time = Instant::now();
+    let solutions = k_best::k_best(problem, k)?;
+    let solve_time = start_time.elapsed().as_millis() as f32 / 1000.0;
+
+    print_ranked(options, problem, &solutions);
+    println!(
+        "Solutions Found: {}, Time Elapsed: {}",
+        solutions.len(),
+        solve_time
+    );
     Ok(())
 }
+
+/// Print every optimal solution up to `limit`, and whether the optimum is unique
+fn print_optimal<T: Integer>(
+    options: &Options,
+    problem: &Problem<T>,
+    limit: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let start_time = Instant::now();
+    let (solutions, more) = k_best::optimal_solutions(problem, limit)?;
+    let solve_time = start_time.elapsed().as_millis() as f32 / 1000.0;
+
+    print_ranked(options, problem, &solutions);
+    let count = if more {
+        format!("more than {}", solutions.len())
+    } else {
+        solutions.len().to_string()
+    };
+    println!(
+        "Optimal Solutions: {}, Unique: {}, Time Elapsed: {}",
+        count,
+        solutions.len() == 1 && !more,
+        solve_time
+    );
+    Ok(())
+}
+
+/// Print every point of the Pareto front, the one with the least first value first
+fn print_front<T: Integer>(
+    options: &Options,
+    problem: &Problem<T>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let start_time = Instant::now();
+    let (front, _) = biobjective::pareto_front(problem, true)?;
+    let solve_time = start_time.elapsed().as_millis() as f32 / 1000.0;
+
+    if options.no_print_solution {
+        println!("Value\tSecond Value\tWeight");
+    } else {
+        println!("Value\tSecond Value\tWeight\tDecision");
+    }
+    for point in &front {
+        let solution = &point.solution;
+        if !solution.validate(problem) {
+            panic!("ERROR: Solution is not valid!");
+        }
+        if options.no_print_solution {
+            println!(
+                "{}\t{}\t{}",
+                solution.value, point.second_value, solution.weight
+            );
+        } else {
+            let decision: String = solution.decision.iter().map(|d| d.to_string()).collect();
+            println!(
+                "{}\t{}\t{}\t{}",
+                solution.value, point.second_value, solution.weight, decision
+            );
+        }
+    }
+    println!(
+        "Pareto Points: {}, Time Elapsed: {}",
+        front.len(),
+        solve_time
+    );
+    Ok(())
+}
+
+fn print_ranked<T: Integer>(options: &Options, problem: &Problem<T>, solutions: &[Solution<T>]) {
+    if options.no_print_solution {
+        println!("Rank\tValue\tWeight");
+    } else {
+        println!("Rank\tValue\tWeight\tDecision");
+    }
+    for (rank, solution) in solutions.iter().enumerate() {
+        if !solution.validate(problem) {
+            panic!("ERROR: Solution is not valid!");
+        }
+        if options.no_print_solution {
+            println!("{}\t{}\t{}", rank + 1, solution.value, solution.weight);
+        } else {
+            let decision: String = solution.decision.iter().map(|d| d.to_string()).collect();
+            println!(
+                "{}\t{}\t{}\t{}",
+                rank + 1,
+                solution.value,
+                solution.weight,
+                decision
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn subset_sum_too_large_for_bitset_falls_back_to_minknap() {
+        let items = vec![
+            Item::new(0, 4_000_000_000_000u64, 4_000_000_000_000),
+            Item::new(1, 3_000_000_000_000, 3_000_000_000_000),
+            Item::new(2, 2_500_000_000_000, 2_500_000_000_000),
+        ];
+        let problem = Problem::new(items, 6_000_000_000_000);
+        let registry = SolverRegistry::default();
+        let config = SolveConfig::default();
+        let (solver, report) =
+            solve_routed(&registry, &Solver::Minknap, &problem, &config).unwrap();
+        assert_eq!(solver.name(), "Minknap");
+        assert_eq!(report.solution.value, 5_500_000_000_000);
+
+        // Small capacities still go to the bitset
+        let items = vec![
+            Item::new(0, 4u64, 4),
+            Item::new(1, 3, 3),
+            Item::new(2, 2, 2),
+        ];
+        let problem = Problem::new(items, 6);
+        let (solver, report) =
+            solve_routed(&registry, &Solver::Minknap, &problem, &config).unwrap();
+        assert_eq!(solver.name(), "SubsetSum");
+        assert_eq!(report.solution.value, 6);
+    }
+
+    #[test]
+    fn subset_sum_weights_beyond_usize_fall_back_to_minknap() {
+        let items = vec![Item::new(0, 1u128 << 65, 1 << 65), Item::new(1, 5, 5)];
+        let problem = Problem::new(items, 1 << 66);
+        let registry = SolverRegistry::default();
+        let config = SolveConfig::default();
+        let (solver, report) =
+            solve_routed(&registry, &Solver::Minknap, &problem, &config).unwrap();
+        assert_eq!(solver.name(), "Minknap");
+        assert_eq!(report.solution.value, (1 << 65) + 5);
+    }
+}
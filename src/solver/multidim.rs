@@ -0,0 +1,424 @@
+// Multidimensional knapsack problem, where every item has a weight in several dimensions
+// and a solution has to fit within the capacity of each of them
+//
+// The surrogate relaxation adds the constraints together, each scaled by a multiplier,
+// into a single 0-1 problem which minknap solves exactly. Every solution of the original
+// problem fits the surrogate problem, so its optimum is an upper bound. The surrogate
+// solution is repaired by dropping the least efficient items until every dimension fits,
+// then greedily filled with whatever still fits. Multipliers of the dimensions the surrogate
+// solution overflows are doubled, and the process repeated a few times.
+//
+// Large problems take the best repaired solution. Small problems are solved exactly by a
+// depth first branch and bound, starting from the repaired solution as the lower bound.
+// Each dimension on its own, and the tightest surrogate, are relaxations too, so the
+// smallest of their linear bounds bounds every branch.
+
+use crate::solver::integer::*;
+use crate::solver::minknap;
+use crate::solver::problem::*;
+use crate::solver::registry::*;
+
+/// Problems with up to this many items are solved exactly
+pub const EXACT_ITEM_LIMIT: usize = 40;
+
+/// Most surrogate problems solved while adjusting the multipliers
+const SURROGATE_ROUNDS: usize = 8;
+
+/// Capacity and item weights of every dimension, including the first
+struct Constraints<T> {
+    capacities: Vec<T>,
+    /// Indexed by dimension, then item
+    weights: Vec<Vec<T>>,
+}
+
+impl<T: Integer> Constraints<T> {
+    fn new(problem: &Problem<T>) -> Constraints<T> {
+        let mut capacities = vec![problem.capacity];
+        let mut weights = vec![problem.items.iter().map(|item| item.weight).collect()];
+        for dimension in &problem.dimensions {
+            capacities.push(dimension.capacity);
+            weights.push(dimension.weights.clone());
+        }
+        Constraints {
+            capacities,
+            weights,
+        }
+    }
+
+    fn dimension_count(&self) -> usize {
+        self.capacities.len()
+    }
+
+    // Problem::check_overflow covers the capacity plus every weight of each dimension,
+    // so the weight used never overflows as long as it stays under the capacity
+    fn fits(&self, index: usize, used: &[T]) -> bool {
+        (0..self.dimension_count()).all(|d| used[d] + self.weights[d][index] <= self.capacities[d])
+    }
+
+    fn add(&self, index: usize, used: &mut [T]) {
+        for (d, used) in used.iter_mut().enumerate() {
+            *used += self.weights[d][index];
+        }
+    }
+
+    fn remove(&self, index: usize, used: &mut [T]) {
+        for (d, used) in used.iter_mut().enumerate() {
+            *used -= self.weights[d][index];
+        }
+    }
+
+    fn usage(&self, decision: &[bool]) -> Vec<T> {
+        let mut used = vec![T::ZERO; self.dimension_count()];
+        for (index, _) in decision.iter().enumerate().filter(|(_, &d)| d) {
+            self.add(index, &mut used);
+        }
+        used
+    }
+
+    fn over_capacity(&self, used: &[T]) -> Vec<bool> {
+        used.iter()
+            .zip(&self.capacities)
+            .map(|(used, capacity)| used > capacity)
+            .collect()
+    }
+
+    /// The single 0-1 problem with every constraint scaled by its multiplier and added up,
+    /// None if the sums do not fit in `T`
+    fn surrogate_problem(&self, problem: &Problem<T>, multipliers: &[T]) -> Option<Problem<T>> {
+        let mut capacity = T::ZERO;
+        for (&c, &u) in self.capacities.iter().zip(multipliers) {
+            capacity = c.checked_mul(u).and_then(|c| capacity.checked_add(c))?;
+        }
+        let mut items = Vec::with_capacity(problem.items.len());
+        for (index, item) in problem.items.iter().enumerate() {
+            let mut weight = T::ZERO;
+            for (weights, &u) in self.weights.iter().zip(multipliers) {
+                weight = weights[index]
+                    .checked_mul(u)
+                    .and_then(|w| weight.checked_add(w))?;
+            }
+            items.push(Item::new(item.id, item.value, weight));
+        }
+        let surrogate = Problem::new(items, capacity);
+        surrogate.check_overflow().ok()?;
+        Some(surrogate)
+    }
+}
+
+fn decision_value<T: Integer>(problem: &Problem<T>, decision: &[bool]) -> T {
+    problem
+        .items
+        .iter()
+        .zip(decision)
+        .filter(|(_, &d)| d)
+        .map(|(item, _)| item.value)
+        .sum()
+}
+
+/// Drop the least efficient items until every dimension fits, then add the most
+/// efficient items that still fit. Efficiency is taken from the surrogate weights
+fn repair<T: Integer>(surrogate: &Problem<T>, constraints: &Constraints<T>, decision: &mut [bool]) {
+    let items = &surrogate.items;
+    let mut order: Vec<usize> = (0..items.len()).collect();
+    order.sort_unstable_by(|&a, &b| items[b].efficiency_cmp(&items[a]));
+
+    let mut used = constraints.usage(decision);
+    for &index in order.iter().rev() {
+        if !constraints.over_capacity(&used).contains(&true) {
+            break;
+        }
+        if decision[index] {
+            decision[index] = false;
+            constraints.remove(index, &mut used);
+        }
+    }
+
+    for &index in &order {
+        if !decision[index] && constraints.fits(index, &used) {
+            decision[index] = true;
+            constraints.add(index, &mut used);
+        }
+    }
+}
+
+struct SurrogateResult<T> {
+    decision: Vec<bool>,
+    value: T,
+    /// Smallest surrogate optimum found
+    upper_bound: T,
+    /// Multipliers of the surrogate problem with that optimum
+    multipliers: Vec<T>,
+}
+
+fn surrogate_heuristic<T: Integer>(
+    problem: &Problem<T>,
+    constraints: &Constraints<T>,
+) -> Result<SurrogateResult<T>, SolveError> {
+    let mut multipliers = vec![T::ONE; constraints.dimension_count()];
+    let mut result = SurrogateResult {
+        decision: vec![false; problem.items.len()],
+        value: T::ZERO,
+        upper_bound: T::MAX,
+        multipliers: multipliers.clone(),
+    };
+
+    for _ in 0..SURROGATE_ROUNDS {
+        // Problem::check_overflow guarantees the first round fits
+        let surrogate = match constraints.surrogate_problem(problem, &multipliers) {
+            Some(surrogate) => surrogate,
+            None => break,
+        };
+        let solution = minknap::solve(&surrogate)?;
+        if solution.value < result.upper_bound {
+            result.upper_bound = solution.value;
+            result.multipliers.clone_from(&multipliers);
+        }
+
+        let mut decision: Vec<bool> = solution.decision.iter().map(|&d| d != T::ZERO).collect();
+        let over = constraints.over_capacity(&constraints.usage(&decision));
+        repair(&surrogate, constraints, &mut decision);
+        let value = decision_value(problem, &decision);
+        if value > result.value {
+            result.decision = decision;
+            result.value = value;
+        }
+
+        // A surrogate solution that fits every dimension is optimal
+        if !over.contains(&true) || result.value == result.upper_bound {
+            break;
+        }
+        for (multiplier, _) in multipliers.iter_mut().zip(over).filter(|(_, o)| *o) {
+            *multiplier = multiplier.saturating_mul(T::ONE + T::ONE);
+        }
+    }
+    Ok(result)
+}
+
+/// A single constraint implied by the problem's constraints, used for linear bounds
+struct Relaxation<T> {
+    multipliers: Vec<T>,
+    /// Weight of each item in the relaxation, indexed by item
+    weights: Vec<T>,
+    /// Positions in the branching order, by decreasing value / weight
+    positions: Vec<usize>,
+}
+
+struct BranchAndBound<'a, T> {
+    constraints: &'a Constraints<T>,
+    values: Vec<T>,
+    /// Item indices in the order they are branched on
+    order: Vec<usize>,
+    relaxations: Vec<Relaxation<T>>,
+    decision: Vec<bool>,
+    used: Vec<T>,
+    best_decision: Vec<bool>,
+    best_value: T,
+    nodes: usize,
+}
+
+impl<'a, T: Integer> BranchAndBound<'a, T> {
+    fn new(
+        problem: &Problem<T>,
+        constraints: &'a Constraints<T>,
+        surrogate: SurrogateResult<T>,
+    ) -> BranchAndBound<'a, T> {
+        // Each dimension on its own, and the tightest surrogate found.
+        // The surrogate comes first so it decides the branching order
+        let count = constraints.dimension_count();
+        let unit_vectors = (0..count).map(|d| {
+            let mut multipliers = vec![T::ZERO; count];
+            multipliers[d] = T::ONE;
+            multipliers
+        });
+        let relaxed: Vec<(Vec<T>, Problem<T>)> = std::iter::once(surrogate.multipliers)
+            .chain(unit_vectors)
+            .filter_map(|m| Some((m.clone(), constraints.surrogate_problem(problem, &m)?)))
+            .collect();
+
+        let items = &relaxed[0].1.items;
+        let mut order: Vec<usize> = (0..items.len()).collect();
+        order.sort_unstable_by(|&a, &b| items[b].efficiency_cmp(&items[a]));
+
+        let relaxations = relaxed
+            .into_iter()
+            .map(|(multipliers, relaxed)| {
+                let items = &relaxed.items;
+                let mut positions: Vec<usize> = (0..order.len()).collect();
+                positions
+                    .sort_unstable_by(|&a, &b| items[order[b]].efficiency_cmp(&items[order[a]]));
+                Relaxation {
+                    multipliers,
+                    weights: items.iter().map(|item| item.weight).collect(),
+                    positions,
+                }
+            })
+            .collect();
+
+        BranchAndBound {
+            constraints,
+            values: problem.items.iter().map(|item| item.value).collect(),
+            order,
+            relaxations,
+            decision: vec![false; problem.items.len()],
+            used: vec![T::ZERO; count],
+            best_decision: surrogate.decision,
+            best_value: surrogate.value,
+            nodes: 0,
+        }
+    }
+
+    /// Smallest of the linear bounds of each relaxation, over the items from `depth` on
+    fn upper_bound(&self, depth: usize, value: T) -> T {
+        let mut bound = T::MAX;
+        for relaxation in &self.relaxations {
+            let mut remaining = T::ZERO;
+            for (d, &u) in relaxation.multipliers.iter().enumerate() {
+                remaining += u * (self.constraints.capacities[d] - self.used[d]);
+            }
+            let mut linear = value;
+            for &position in relaxation.positions.iter().filter(|&&p| p >= depth) {
+                let index = self.order[position];
+                let weight = relaxation.weights[index];
+                if weight <= remaining {
+                    remaining -= weight;
+                    linear += self.values[index];
+                } else {
+                    linear += mul_div_floor(remaining, self.values[index], weight);
+                    break;
+                }
+            }
+            bound = bound.min(linear);
+        }
+        bound
+    }
+
+    fn branch(&mut self, depth: usize, value: T) {
+        self.nodes += 1;
+        if value > self.best_value {
+            self.best_value = value;
+            self.best_decision.copy_from_slice(&self.decision);
+        }
+        if depth == self.order.len() || self.upper_bound(depth, value) <= self.best_value {
+            return;
+        }
+
+        let index = self.order[depth];
+        if self.constraints.fits(index, &self.used) {
+            self.decision[index] = true;
+            self.constraints.add(index, &mut self.used);
+            self.branch(depth + 1, value + self.values[index]);
+            self.constraints.remove(index, &mut self.used);
+            self.decision[index] = false;
+        }
+        self.branch(depth + 1, value);
+    }
+}
+
+/// Exact branch and bound for small problems, surrogate relaxation heuristic for large ones
+pub struct Multidim;
+
+impl<T: Integer> KnapsackSolver<T> for Multidim {
+    fn name(&self) -> &'static str {
+        "Multidim"
+    }
+
+    fn solve(
+        &self,
+        problem: &Problem<T>,
+        config: &SolveConfig,
+    ) -> Result<SolveReport<T>, SolveError> {
+        problem.check_supported("Multidim", &[Extension::Dimensions])?;
+        if problem.fixings.is_some() {
+            return solve_fixed(self, problem, config);
+        }
+        problem.check_overflow()?;
+
+        let constraints = Constraints::new(problem);
+        let surrogate = surrogate_heuristic(problem, &constraints)?;
+        if config.verbose {
+            println!(
+                "dimensions: {}, surrogate solution value: {}, surrogate upper bound: {}",
+                constraints.dimension_count(),
+                surrogate.value,
+                surrogate.upper_bound
+            );
+        }
+
+        let exact = problem.items.len() <= EXACT_ITEM_LIMIT;
+        let (decision, value, states_explored) = if exact && surrogate.value < surrogate.upper_bound
+        {
+            let mut search = BranchAndBound::new(problem, &constraints, surrogate);
+            search.branch(0, T::ZERO);
+            if config.verbose {
+                println!("branch and bound nodes: {}", search.nodes);
+            }
+            (search.best_decision, search.best_value, Some(search.nodes))
+        } else {
+            (surrogate.decision, surrogate.value, None)
+        };
+
+        let weight = constraints.usage(&decision)[0];
+        Ok(SolveReport {
+            solution: Solution::from_bools(&decision, value, weight),
+            states_explored,
+            bytes_used: None,
+        })
+    }
+}
+
+pub fn solve<T: Integer>(problem: &Problem<T>) -> Result<Solution<T>, SolveError> {
+    let report = Multidim.solve(problem, &SolveConfig::default())?;
+    Ok(report.solution)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::solver::test_util::{subsets, Lcg};
+
+    /// Pseudo random problem with three dimensions
+    fn example_problem(item_count: usize, seed: u64) -> Problem<u64> {
+        let mut rng = Lcg::new(seed);
+        let items = rng.items(item_count, 1..51, 0..30);
+        let mut problem = Problem::new(items, 8 * item_count as u64);
+        for capacity in [6 * item_count as u64, 7 * item_count as u64] {
+            let weights = (0..item_count).map(|_| rng.below(30)).collect();
+            problem.dimensions.push(Dimension { capacity, weights });
+        }
+        problem
+    }
+
+    fn brute_force(problem: &Problem<u64>) -> u64 {
+        let constraints = Constraints::new(problem);
+        subsets(problem)
+            .filter(|(taken, _, _)| {
+                let used = constraints.usage(taken);
+                !constraints.over_capacity(&used).contains(&true)
+            })
+            .map(|(_, value, _)| value)
+            .max()
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        for seed in 0..20 {
+            let problem = example_problem(14, seed);
+            let solution = solve(&problem).unwrap();
+            assert!(solution.validate(&problem));
+            assert_eq!(solution.value, brute_force(&problem), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn surrogate_bounds_large_problems() {
+        let problem = example_problem(500, 7);
+        let constraints = Constraints::new(&problem);
+        let surrogate = surrogate_heuristic(&problem, &constraints).unwrap();
+        assert!(surrogate.value <= surrogate.upper_bound);
+
+        let solution = solve(&problem).unwrap();
+        assert!(solution.validate(&problem));
+        assert_eq!(solution.value, surrogate.value);
+    }
+}
@@ -0,0 +1,363 @@
+// Multiple knapsack problem, where items are packed into several bins with their own
+// capacities, and each item goes in at most one bin
+//
+// Packing every bin at once into a single knapsack holding the sum of their capacities is
+// the surrogate relaxation, and its optimum, found with minknap, is an upper bound.
+// A lower bound comes from filling the bins one at a time, smallest first, each with the
+// optimal minknap packing of the items still left.
+//
+// The exact mode is the bound-and-bound scheme of Martello and Toth. At every node both
+// bounds are computed. If the lower bound reaches the upper bound the node is solved,
+// otherwise the search branches on the first item the lower bound put in the current bin:
+// first packing it in that bin, then keeping it out of that bin. Once the lower bound puts
+// nothing more in the current bin, the search moves on to the next bin.
+
+use crate::solver::integer::*;
+use crate::solver::minknap;
+use crate::solver::problem::*;
+use crate::solver::registry::*;
+
+/// Bins, smallest first, along with the items in order of efficiency
+struct Bins<'a, T> {
+    problem: &'a Problem<T>,
+    capacities: Vec<T>,
+    /// Index in `Problem::bin_capacities` of each sorted bin
+    original: Vec<usize>,
+    /// Item indices, most efficient first
+    order: Vec<usize>,
+}
+
+/// Items packed into bins, and their total value
+#[derive(Debug, Clone)]
+struct Packing<T> {
+    /// Sorted bin of each item, None for items left out
+    bins: Vec<Option<usize>>,
+    value: T,
+}
+
+impl<'a, T: Integer> Bins<'a, T> {
+    fn new(problem: &'a Problem<T>) -> Bins<'a, T> {
+        let all_capacities = problem.bin_capacities();
+        let mut original: Vec<usize> = (0..all_capacities.len()).collect();
+        original.sort_by_key(|&bin| all_capacities[bin]);
+        let capacities = original.iter().map(|&bin| all_capacities[bin]).collect();
+
+        let items = &problem.items;
+        let mut order: Vec<usize> = (0..items.len()).collect();
+        order.sort_unstable_by(|&a, &b| items[b].efficiency_cmp(&items[a]));
+
+        Bins {
+            problem,
+            capacities,
+            original,
+            order,
+        }
+    }
+
+    /// Optimal minknap packing of `items` into a single knapsack,
+    /// returns the indices of the items taken and their value
+    fn fill(&self, items: &[usize], capacity: T) -> Result<(Vec<usize>, T), SolveError> {
+        let sub_items = items
+            .iter()
+            .map(|&index| {
+                let item = &self.problem.items[index];
+                Item::new(item.id, item.value, item.weight)
+            })
+            .collect();
+        let solution = minknap::solve(&Problem::new(sub_items, capacity))?;
+        let taken = items
+            .iter()
+            .zip(&solution.decision)
+            .filter(|(_, &d)| d != T::ZERO)
+            .map(|(&index, _)| index)
+            .collect();
+        Ok((taken, solution.value))
+    }
+
+    /// Unpacked items, most efficient first
+    fn free_items(&self, bins: &[Option<usize>]) -> Vec<usize> {
+        self.order
+            .iter()
+            .copied()
+            .filter(|&index| bins[index].is_none())
+            .collect()
+    }
+
+    /// Surrogate bound on the value the free items can add, with `residual` left in
+    /// bin `bin` and every later bin still empty
+    fn upper_bound(
+        &self,
+        bins: &[Option<usize>],
+        bin: usize,
+        residual: T,
+    ) -> Result<T, SolveError> {
+        let capacity = self.capacities[bin + 1..]
+            .iter()
+            .fold(residual, |sum, &c| sum + c);
+        Ok(self.fill(&self.free_items(bins), capacity)?.1)
+    }
+
+    /// Fill the bins from `bin` on in turn, starting with the items already packed in `bins`
+    fn fill_from(&self, bins: &mut Packing<T>, bin: usize) -> Result<(), SolveError> {
+        for (bin, &capacity) in self.capacities.iter().enumerate().skip(bin) {
+            let (taken, value) = self.fill(&self.free_items(&bins.bins), capacity)?;
+            for index in taken {
+                bins.bins[index] = Some(bin);
+            }
+            bins.value += value;
+        }
+        Ok(())
+    }
+
+    fn solution(&self, packing: &Packing<T>) -> Solution<T> {
+        let mut weight = T::ZERO;
+        let mut decision = vec![T::ZERO; packing.bins.len()];
+        let mut bins = vec![None; packing.bins.len()];
+        for (index, bin) in packing.bins.iter().enumerate() {
+            if let Some(bin) = bin {
+                weight += self.problem.items[index].weight;
+                decision[index] = T::ONE;
+                bins[index] = Some(self.original[*bin]);
+            }
+        }
+        Solution {
+            decision,
+            value: packing.value,
+            weight,
+            bins: Some(bins),
+        }
+    }
+}
+
+struct BoundAndBound<'a, 'b, T> {
+    bins: &'b Bins<'a, T>,
+    /// Items packed by the branches taken so far
+    packing: Packing<T>,
+    /// Items kept out of the current bin
+    excluded: Vec<bool>,
+    best: Packing<T>,
+    nodes: usize,
+}
+
+impl<'a, 'b, T: Integer> BoundAndBound<'a, 'b, T> {
+    fn node(&mut self, bin: usize, residual: T) -> Result<(), SolveError> {
+        self.nodes += 1;
+        let upper_bound =
+            self.packing.value + self.bins.upper_bound(&self.packing.bins, bin, residual)?;
+        if upper_bound <= self.best.value {
+            return Ok(());
+        }
+
+        // Lower bound, the rest of this bin without the excluded items, then the later bins
+        let candidates: Vec<usize> = self
+            .bins
+            .free_items(&self.packing.bins)
+            .into_iter()
+            .filter(|&index| !self.excluded[index])
+            .collect();
+        let (taken, value) = self.bins.fill(&candidates, residual)?;
+        let mut lower = self.packing.clone();
+        for &index in &taken {
+            lower.bins[index] = Some(bin);
+        }
+        lower.value += value;
+        self.bins.fill_from(&mut lower, bin + 1)?;
+        if lower.value > self.best.value {
+            self.best = lower;
+        }
+        if self.best.value >= upper_bound {
+            return Ok(());
+        }
+
+        match taken.first() {
+            Some(&index) => {
+                let item = self.bins.problem.items[index];
+                self.packing.bins[index] = Some(bin);
+                self.packing.value += item.value;
+                self.node(bin, residual - item.weight)?;
+                self.packing.value -= item.value;
+                self.packing.bins[index] = None;
+
+                self.excluded[index] = true;
+                self.node(bin, residual)?;
+                self.excluded[index] = false;
+            }
+            None if bin + 1 < self.bins.capacities.len() => {
+                let fresh = vec![false; self.excluded.len()];
+                let excluded = std::mem::replace(&mut self.excluded, fresh);
+                self.node(bin + 1, self.bins.capacities[bin + 1])?;
+                self.excluded = excluded;
+            }
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+fn solve_with_config<T: Integer>(
+    problem: &Problem<T>,
+    config: &SolveConfig,
+    exact: bool,
+) -> Result<SolveReport<T>, SolveError> {
+    let name = if exact { "MultipleExact" } else { "Multiple" };
+    problem.check_supported(name, &[Extension::Bins])?;
+    if problem.fixings.is_some() {
+        let solver: &dyn KnapsackSolver<T> = if exact { &MultipleExact } else { &Multiple };
+        return solve_fixed(solver, problem, config);
+    }
+    problem.check_overflow()?;
+
+    let bins = Bins::new(problem);
+    let empty = Packing {
+        bins: vec![None; problem.items.len()],
+        value: T::ZERO,
+    };
+    let (best, states_explored) = if exact {
+        let mut search = BoundAndBound {
+            bins: &bins,
+            packing: empty.clone(),
+            excluded: vec![false; problem.items.len()],
+            best: empty,
+            nodes: 0,
+        };
+        search.node(0, bins.capacities[0])?;
+        (search.best, Some(search.nodes))
+    } else {
+        let mut packing = empty;
+        bins.fill_from(&mut packing, 0)?;
+        (packing, None)
+    };
+
+    if config.verbose {
+        let upper_bound =
+            bins.upper_bound(&vec![None; problem.items.len()], 0, bins.capacities[0])?;
+        println!(
+            "bins: {}, packed value: {}, surrogate upper bound: {}",
+            bins.capacities.len(),
+            best.value,
+            upper_bound
+        );
+    }
+
+    Ok(SolveReport {
+        solution: bins.solution(&best),
+        states_explored,
+        bytes_used: None,
+    })
+}
+
+/// Fills bins one at a time with minknap, smallest first
+pub struct Multiple;
+
+impl<T: Integer> KnapsackSolver<T> for Multiple {
+    fn name(&self) -> &'static str {
+        "Multiple"
+    }
+
+    fn solve(
+        &self,
+        problem: &Problem<T>,
+        config: &SolveConfig,
+    ) -> Result<SolveReport<T>, SolveError> {
+        solve_with_config(problem, config, false)
+    }
+}
+
+/// Martello and Toth's bound-and-bound, with minknap for the bounds
+pub struct MultipleExact;
+
+impl<T: Integer> KnapsackSolver<T> for MultipleExact {
+    fn name(&self) -> &'static str {
+        "MultipleExact"
+    }
+
+    fn solve(
+        &self,
+        problem: &Problem<T>,
+        config: &SolveConfig,
+    ) -> Result<SolveReport<T>, SolveError> {
+        solve_with_config(problem, config, true)
+    }
+}
+
+pub fn solve<T: Integer>(problem: &Problem<T>) -> Result<Solution<T>, SolveError> {
+    let report = Multiple.solve(problem, &SolveConfig::default())?;
+    Ok(report.solution)
+}
+
+pub fn solve_exact<T: Integer>(problem: &Problem<T>) -> Result<Solution<T>, SolveError> {
+    let report = MultipleExact.solve(problem, &SolveConfig::default())?;
+    Ok(report.solution)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    fn example_problem(capacities: &[u64]) -> Problem<u64> {
+        let items = [
+            (78, 18),
+            (35, 9),
+            (89, 23),
+            (36, 20),
+            (94, 59),
+            (75, 61),
+            (74, 70),
+            (79, 75),
+            (80, 76),
+            (16, 30),
+        ]
+        .iter()
+        .enumerate()
+        .map(|(id, &(value, weight))| Item::new(id, value, weight))
+        .collect();
+        let mut problem = Problem::new(items, capacities[0]);
+        problem.bins = capacities[1..].to_vec();
+        problem
+    }
+
+    /// Try every bin, or none, for every item
+    fn brute_force(problem: &Problem<u64>) -> u64 {
+        let capacities = problem.bin_capacities();
+        let choices = capacities.len() + 1;
+        let n = problem.items.len();
+        let mut best = 0;
+        for code in 0..choices.pow(n as u32) {
+            let mut loads = vec![0; capacities.len()];
+            let mut value = 0;
+            let mut rest = code;
+            for item in &problem.items {
+                let choice = rest % choices;
+                rest /= choices;
+                if choice > 0 {
+                    loads[choice - 1] += item.weight;
+                    value += item.value;
+                }
+            }
+            if loads.iter().zip(&capacities).all(|(l, c)| l <= c) {
+                best = best.max(value);
+            }
+        }
+        best
+    }
+
+    #[test]
+    fn exact_matches_brute_force() {
+        for capacities in [vec![103, 156], vec![90, 40, 60], vec![10, 5], vec![500, 20]] {
+            let problem = example_problem(&capacities);
+            let solution = solve_exact(&problem).unwrap();
+            assert!(solution.validate(&problem));
+            assert_eq!(solution.value, brute_force(&problem), "{:?}", capacities);
+        }
+    }
+
+    #[test]
+    fn heuristic_packs_every_bin() {
+        let problem = example_problem(&[156, 103]);
+        let solution = solve(&problem).unwrap();
+        assert!(solution.validate(&problem));
+        let bins = solution.bins.unwrap();
+        assert!(bins.contains(&Some(0)) && bins.contains(&Some(1)));
+        assert!(solution.value <= brute_force(&problem));
+    }
+}
@@ -0,0 +1,325 @@
+// Helpers for reading problem files
+// Every error carries the line and column it was found at,
+// so that problems with files produced by other tools are easy to track down
+
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::iter::{Enumerate, Peekable};
+
+#[derive(Debug)]
+pub enum ParseErrorCause {
+    /// Reading from the input failed
+    Io(std::io::Error),
+    /// The line ended, or the input ended, before an expected token
+    MissingToken(&'static str),
+    /// A token that should have been a non-negative integer
+    NonNumeric {
+        expected: &'static str,
+        token: String,
+    },
+    /// A number too large for the integer type being read
+    TooLarge {
+        expected: &'static str,
+        token: String,
+    },
+    /// An item count, id or class too large for a usize, whatever the integer type
+    OutOfRange {
+        expected: &'static str,
+        token: String,
+    },
+    /// Fewer items were listed than the item count promised
+    CountMismatch { expected: usize, found: usize },
+    /// Unexpected tokens after a complete line, or lines after the capacity
+    TrailingGarbage(String),
+    /// A line after the capacity started with a keyword we do not know
+    UnknownKeyword(String),
+    /// A line after the capacity referred to an item id that was not listed
+    UnknownItem(usize),
+    /// A line after the capacity referred to an item id shared by several items
+    DuplicateItem(usize),
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    /// 1 indexed line of the input
+    pub line: usize,
+    /// 1 indexed column of the input, in characters
+    pub column: usize,
+    pub cause: ParseErrorCause,
+}
+
+impl ParseError {
+    pub fn new(line: usize, column: usize, cause: ParseErrorCause) -> ParseError {
+        ParseError {
+            line,
+            column,
+            cause,
+        }
+    }
+}
+
+impl std::fmt::Display for ParseErrorCause {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseErrorCause::Io(e) => write!(f, "could not read input: {}", e),
+            ParseErrorCause::MissingToken(expected) => write!(f, "missing {}", expected),
+            ParseErrorCause::NonNumeric { expected, token } => {
+                write!(f, "expected {} to be a number, found '{}'", expected, token)
+            }
+            ParseErrorCause::TooLarge { expected, token } => {
+                write!(
+                    f,
+                    "{} {} is too large for the integer type",
+                    expected, token
+                )
+            }
+            ParseErrorCause::OutOfRange { expected, token } => {
+                write!(f, "{} {} is out of range", expected, token)
+            }
+            ParseErrorCause::CountMismatch { expected, found } => write!(
+                f,
+                "item count says {} items, but only {} were listed",
+                expected, found
+            ),
+            ParseErrorCause::TrailingGarbage(token) => {
+                write!(f, "unexpected trailing input '{}'", token)
+            }
+            ParseErrorCause::UnknownKeyword(keyword) => write!(f, "unknown keyword '{}'", keyword),
+            ParseErrorCause::UnknownItem(id) => write!(f, "no item with id {}", id),
+            ParseErrorCause::DuplicateItem(id) => {
+                write!(f, "item id {} is used by more than one item", id)
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.cause
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// A non-blank line of input, split into tokens
+pub struct Line {
+    number: usize,
+    tokens: Vec<(usize, String)>,
+    next_token: usize,
+    end_column: usize,
+}
+
+impl Line {
+    fn new(number: usize, text: &str) -> Line {
+        let mut tokens = Vec::new();
+        let mut current: Option<(usize, String)> = None;
+        let mut column = 0;
+        for (i, c) in text.chars().enumerate() {
+            column = i + 1;
+            if c.is_whitespace() {
+                tokens.extend(current.take());
+            } else {
+                current
+                    .get_or_insert_with(|| (i + 1, String::new()))
+                    .1
+                    .push(c);
+            }
+        }
+        tokens.extend(current);
+
+        Line {
+            number,
+            tokens,
+            next_token: 0,
+            end_column: column + 1,
+        }
+    }
+
+    pub fn number(&self) -> usize {
+        self.number
+    }
+
+    pub fn token_count(&self) -> usize {
+        self.tokens.len()
+    }
+
+    /// Column of the next token, or just past the end of the line
+    pub fn column(&self) -> usize {
+        match self.tokens.get(self.next_token) {
+            Some((column, _)) => *column,
+            None => self.end_column,
+        }
+    }
+
+    pub fn error(&self, cause: ParseErrorCause) -> ParseError {
+        ParseError::new(self.number, self.column(), cause)
+    }
+
+    /// Parse the next token as a number, `expected` names it for error messages
+    pub fn number_token<T: std::str::FromStr>(
+        &mut self,
+        expected: &'static str,
+    ) -> Result<T, ParseError> {
+        let (column, token) = match self.tokens.get(self.next_token) {
+            Some(t) => t,
+            None => return Err(self.error(ParseErrorCause::MissingToken(expected))),
+        };
+        let value = token.parse::<T>().map_err(|_| {
+            let token = token.clone();
+            // All digits means the number was fine, the type was too small
+            let cause = if token.chars().all(|c| c.is_ascii_digit()) {
+                ParseErrorCause::TooLarge { expected, token }
+            } else {
+                ParseErrorCause::NonNumeric { expected, token }
+            };
+            ParseError::new(self.number, *column, cause)
+        })?;
+        self.next_token += 1;
+        Ok(value)
+    }
+
+    /// Parse the next token as an item count, id or class. Unlike values and weights these
+    /// are usize whatever the integer type, so one too large for it is out of range
+    pub fn count_token(&mut self, expected: &'static str) -> Result<usize, ParseError> {
+        self.number_token::<usize>(expected).map_err(|e| {
+            let cause = match e.cause {
+                ParseErrorCause::TooLarge { expected, token } => {
+                    ParseErrorCause::OutOfRange { expected, token }
+                }
+                cause => cause,
+            };
+            ParseError::new(e.line, e.column, cause)
+        })
+    }
+
+    /// The next token should be one of the `known` keywords.
+    /// A number is reported as trailing garbage, since plain problem files end at the capacity
+    pub fn keyword_token(&mut self, known: &[&'static str]) -> Result<&'static str, ParseError> {
+        let token = match self.tokens.get(self.next_token) {
+            Some((_, token)) => token,
+            None => return Err(self.error(ParseErrorCause::MissingToken("keyword"))),
+        };
+        if token.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(self.error(ParseErrorCause::TrailingGarbage(token.clone())));
+        }
+        match known.iter().find(|k| *k == token) {
+            Some(keyword) => {
+                self.next_token += 1;
+                Ok(keyword)
+            }
+            None => Err(self.error(ParseErrorCause::UnknownKeyword(token.clone()))),
+        }
+    }
+
+    /// True if some tokens on the line have not been read yet
+    pub fn has_tokens_left(&self) -> bool {
+        self.next_token < self.tokens.len()
+    }
+
+    /// Error if there are any tokens left on the line
+    pub fn finish(&self) -> Result<(), ParseError> {
+        match self.tokens.get(self.next_token) {
+            Some((_, token)) => Err(self.error(ParseErrorCause::TrailingGarbage(token.clone()))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Iterates over the non-blank lines of the input
+pub struct LineReader<F: BufRead> {
+    lines: Peekable<Enumerate<std::io::Lines<F>>>,
+    last_line: usize,
+}
+
+impl<F: BufRead> LineReader<F> {
+    pub fn new(input: F) -> LineReader<F> {
+        LineReader {
+            lines: input.lines().enumerate().peekable(),
+            last_line: 0,
+        }
+    }
+
+    /// Line number to report for errors found at the end of the input
+    pub fn end_line(&self) -> usize {
+        self.last_line + 1
+    }
+
+    pub fn next_line(&mut self) -> Result<Option<Line>, ParseError> {
+        for (index, text) in self.lines.by_ref() {
+            let number = index + 1;
+            self.last_line = number;
+            let text = text.map_err(|e| ParseError::new(number, 1, ParseErrorCause::Io(e)))?;
+            if !text.trim().is_empty() {
+                return Ok(Some(Line::new(number, &text)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Like `next_line`, but a missing line is an error
+    pub fn expect_line(&mut self, expected: &'static str) -> Result<Line, ParseError> {
+        match self.next_line()? {
+            Some(line) => Ok(line),
+            None => Err(ParseError::new(
+                self.end_line(),
+                1,
+                ParseErrorCause::MissingToken(expected),
+            )),
+        }
+    }
+
+    /// True if there are no non-blank lines left
+    pub fn at_end(&mut self) -> bool {
+        while let Some((index, Ok(text))) = self.lines.peek() {
+            if !text.trim().is_empty() {
+                return false;
+            }
+            self.last_line = index + 1;
+            self.lines.next();
+        }
+        self.lines.peek().is_none()
+    }
+}
+
+/// Maps item ids to their index in the item list,
+/// for lines that refer to items by id
+pub struct IdIndex {
+    // None for ids that more than one item uses
+    indices: HashMap<usize, Option<usize>>,
+}
+
+impl IdIndex {
+    pub fn new(ids: impl Iterator<Item = usize>) -> IdIndex {
+        let mut indices = HashMap::new();
+        for (index, id) in ids.enumerate() {
+            indices
+                .entry(id)
+                .and_modify(|i| *i = None)
+                .or_insert(Some(index));
+        }
+        IdIndex { indices }
+    }
+
+    /// Parse the next token as an item id, and return that item's index
+    pub fn item_token(&self, line: &mut Line, expected: &'static str) -> Result<usize, ParseError> {
+        let column = line.column();
+        let id = line.count_token(expected)?;
+        match self.indices.get(&id) {
+            Some(Some(index)) => Ok(*index),
+            Some(None) => Err(ParseError::new(
+                line.number(),
+                column,
+                ParseErrorCause::DuplicateItem(id),
+            )),
+            None => Err(ParseError::new(
+                line.number(),
+                column,
+                ParseErrorCause::UnknownItem(id),
+            )),
+        }
+    }
+}
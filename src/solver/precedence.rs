@@ -0,0 +1,553 @@
+// Precedence constrained knapsack problem, where an item can only be taken along with
+// every item that precedes it
+//
+// When no item has more than one predecessor the precedences form a forest, which is
+// solved by dynamic programming over the items in depth first order. Leaving an item out
+// leaves its whole subtree out, so each row of the table only depends on the next row and
+// the row just past the item's subtree. Like the `Dynamic` solver, the table has a column
+// for every unit of capacity.
+//
+// Other precedences, and forests whose table would be too large, are solved by a depth
+// first branch and bound over the items in precedence order, starting from the repaired
+// greedy solution as the lower bound. Items with a predecessor left out are left out too,
+// and each branch is bounded by the linear relaxation over the items still open, the
+// Dantzig bound minknap uses. The minknap optimum without the precedences bounds every
+// solution, and ends the search when reached. Each branch scans every item for its bound,
+// so only problems with up to `EXACT_ITEM_LIMIT` items are searched. Larger ones are too
+// large, unless the repaired greedy solution already reaches the minknap bound.
+
+use crate::solver::dynamic::Array;
+use crate::solver::integer::*;
+use crate::solver::minknap;
+use crate::solver::problem::*;
+use crate::solver::registry::*;
+use std::collections::BTreeSet;
+
+/// Problems with up to this many items are searched exactly when the precedences do not
+/// form a forest, larger ones are too large
+pub const EXACT_ITEM_LIMIT: usize = 200;
+
+/// Precedences of a problem, as lists for each item
+pub(crate) struct Precedences {
+    /// Every item, after the items preceding it
+    order: Vec<usize>,
+    /// Indexed by item, the items that have to be taken before it
+    predecessors: Vec<Vec<usize>>,
+    /// Indexed by item, the items it has to be taken before
+    successors: Vec<Vec<usize>>,
+}
+
+impl Precedences {
+    pub(crate) fn new<T: Integer>(problem: &Problem<T>) -> Result<Precedences, SolveError> {
+        let order = problem.precedence_order()?;
+        let mut predecessors = vec![Vec::new(); problem.items.len()];
+        let mut successors = vec![Vec::new(); problem.items.len()];
+        for &(before, after) in &problem.precedences {
+            predecessors[after].push(before);
+            successors[before].push(after);
+        }
+        for list in predecessors.iter_mut().chain(successors.iter_mut()) {
+            list.sort_unstable();
+            list.dedup();
+        }
+        Ok(Precedences {
+            order,
+            predecessors,
+            successors,
+        })
+    }
+
+    fn is_forest(&self) -> bool {
+        self.predecessors.iter().all(|p| p.len() <= 1)
+    }
+
+    /// The item at `index` and every item before it that is not taken yet, None if they
+    /// weigh more than `room` or any of them is blocked
+    fn missing_ancestors<T: Integer>(
+        &self,
+        problem: &Problem<T>,
+        index: usize,
+        decision: &[bool],
+        blocked: &[bool],
+        room: T,
+    ) -> Option<Vec<usize>> {
+        let mut missing = BTreeSet::new();
+        let mut weight = T::ZERO;
+        let mut stack = vec![index];
+        while let Some(index) = stack.pop() {
+            if decision[index] || !missing.insert(index) {
+                continue;
+            }
+            weight += problem.items[index].weight;
+            if blocked[index] || weight > room {
+                return None;
+            }
+            stack.extend(&self.predecessors[index]);
+        }
+        Some(missing.into_iter().collect())
+    }
+
+    /// Make a decision respect the precedences. Taken items with a predecessor left out
+    /// are left out, then items are tried in `order` once more, each taken along with
+    /// the items before it that are missing whenever they all fit
+    pub(crate) fn repair<T: Integer>(
+        &self,
+        problem: &Problem<T>,
+        order: &[usize],
+        decision: &mut [bool],
+    ) {
+        for &index in &self.order {
+            if decision[index] && self.predecessors[index].iter().any(|&p| !decision[p]) {
+                decision[index] = false;
+            }
+        }
+
+        // Problem::check_overflow guarantees the sum of every weight fits
+        let mut weight: T = decision
+            .iter()
+            .zip(&problem.items)
+            .filter(|(&taken, _)| taken)
+            .map(|(_, item)| item.weight)
+            .sum();
+        // The room left only shrinks, so an item that does not fit along with its missing
+        // predecessors never will, nor will any item after it
+        let mut blocked = vec![false; problem.items.len()];
+        for &index in order {
+            if decision[index] {
+                continue;
+            }
+            let room = problem.capacity - weight;
+            match self.missing_ancestors(problem, index, decision, &blocked, room) {
+                Some(missing) => {
+                    for i in missing {
+                        weight += problem.items[i].weight;
+                        decision[i] = true;
+                    }
+                }
+                None => blocked[index] = true,
+            }
+        }
+    }
+}
+
+/// Items in depth first order through the precedence forest,
+/// along with the position just past each item's subtree
+fn depth_first_order(precedences: &Precedences) -> (Vec<usize>, Vec<usize>) {
+    let item_count = precedences.predecessors.len();
+    let mut order = Vec::with_capacity(item_count);
+    let mut subtree_ends = vec![0; item_count];
+    for root in (0..item_count).filter(|&i| precedences.predecessors[i].is_empty()) {
+        // Each entry is an item, and whether its subtree has been visited
+        let mut stack = vec![(root, false)];
+        while let Some((index, visited)) = stack.pop() {
+            if visited {
+                subtree_ends[index] = order.len();
+                continue;
+            }
+            order.push(index);
+            stack.push((index, true));
+            for &child in precedences.successors[index].iter().rev() {
+                stack.push((child, false));
+            }
+        }
+    }
+    let ends = order.iter().map(|&index| subtree_ends[index]).collect();
+    (order, ends)
+}
+
+fn solve_forest<T: Integer>(
+    problem: &Problem<T>,
+    precedences: &Precedences,
+    verbose: bool,
+) -> Result<Vec<bool>, SolveError> {
+    let (order, ends) = depth_first_order(precedences);
+    let width = match problem.capacity.to_usize() {
+        Some(capacity) => capacity.saturating_add(1),
+        None => usize::MAX,
+    };
+    let height = order.len() + 1;
+    let mut table = Array::new(width, height, verbose)?;
+
+    // Row y holds the best value of the items from position y on, skipping every subtree
+    // whose root is left out. The last row, past every item, stays zero
+    for y in (0..order.len()).rev() {
+        let item = &problem.items[order[y]];
+        // Anything too big for a usize is certainly too big for the table
+        let item_weight = item.weight.to_usize().unwrap_or(usize::MAX);
+        for x in 0..width {
+            let leave_subtree = table.get(x, ends[y]);
+            let take = if item_weight <= x {
+                table.get(x - item_weight, y + 1) + item.value
+            } else {
+                T::ZERO
+            };
+            table.set(x, y, take.max(leave_subtree));
+        }
+    }
+
+    let mut decision = vec![false; problem.items.len()];
+    let mut x = width - 1;
+    let mut y = 0;
+    while y < order.len() {
+        if table.get(x, y) == table.get(x, ends[y]) {
+            y = ends[y];
+        } else {
+            decision[order[y]] = true;
+            // Only items that fit in the table can be taken
+            x -= problem.items[order[y]].weight.to_usize().unwrap();
+            y += 1;
+        }
+    }
+    Ok(decision)
+}
+
+struct BranchAndBound<'a, T> {
+    problem: &'a Problem<T>,
+    precedences: &'a Precedences,
+    /// Indexed by item, its position in the precedence order
+    positions: Vec<usize>,
+    /// Every item, most efficient first
+    efficiency_order: Vec<usize>,
+    taken: Vec<bool>,
+    value: T,
+    weight: T,
+    best_decision: Vec<bool>,
+    best_value: T,
+    /// No solution is worth more than this, so reaching it ends the search
+    upper_bound: T,
+    nodes: usize,
+}
+
+impl<'a, T: Integer> BranchAndBound<'a, T> {
+    /// Items left out before `position`, or items with a predecessor left out, are closed
+    fn is_open(&self, index: usize, position: usize) -> bool {
+        self.positions[index] >= position
+            && self.precedences.predecessors[index]
+                .iter()
+                .all(|&p| self.taken[p] || self.positions[p] >= position)
+    }
+
+    /// Value of the branch's linear relaxation, taking the open items
+    /// in order of efficiency, and the break item's share of the residual capacity
+    fn bound(&self, position: usize) -> T {
+        let mut residual = self.problem.capacity - self.weight;
+        let mut value = self.value;
+        for &index in &self.efficiency_order {
+            if !self.is_open(index, position) {
+                continue;
+            }
+            let item = &self.problem.items[index];
+            if item.weight <= residual {
+                residual -= item.weight;
+                value += item.value;
+            } else {
+                return value.saturating_add(mul_div_floor(residual, item.value, item.weight));
+            }
+        }
+        value
+    }
+
+    /// Record the branch if it beats the best solution, and tell whether any
+    /// of its children could beat it
+    fn visit(&mut self, position: usize) -> bool {
+        self.nodes += 1;
+        if self.value > self.best_value {
+            self.best_value = self.value;
+            self.best_decision.clone_from(&self.taken);
+        }
+        position < self.precedences.order.len()
+            && self.best_value < self.upper_bound
+            && self.bound(position) > self.best_value
+    }
+
+    fn set_taken(&mut self, position: usize, taken: bool) {
+        let index = self.precedences.order[position];
+        let item = self.problem.items[index];
+        self.taken[index] = taken;
+        // Problem::check_overflow guarantees the sums of values and weights fit
+        if taken {
+            self.value += item.value;
+            self.weight += item.weight;
+        } else {
+            self.value -= item.value;
+            self.weight -= item.weight;
+        }
+    }
+
+    /// Depth first search, taking each item before leaving it out. The path is kept as
+    /// an explicit stack of the positions decided and whether their item is taken,
+    /// so long precedence orders can not overflow the call stack
+    fn search(&mut self) {
+        let mut path: Vec<(usize, bool)> = Vec::new();
+        let mut position = 0;
+        loop {
+            if self.visit(position) {
+                let index = self.precedences.order[position];
+                let weight = self.problem.items[index].weight;
+                let take =
+                    self.is_open(index, position) && weight <= self.problem.capacity - self.weight;
+                if take {
+                    self.set_taken(position, true);
+                }
+                path.push((position, take));
+                position += 1;
+                continue;
+            }
+
+            // Back up to the last item taken, and leave it out instead
+            loop {
+                match path.pop() {
+                    Some((taken_position, true)) => {
+                        self.set_taken(taken_position, false);
+                        path.push((taken_position, false));
+                        position = taken_position + 1;
+                        break;
+                    }
+                    Some((_, false)) => {}
+                    None => return,
+                }
+            }
+        }
+    }
+}
+
+fn solve_dag<T: Integer>(
+    problem: &Problem<T>,
+    precedences: &Precedences,
+    verbose: bool,
+) -> Result<(Vec<bool>, Option<usize>), SolveError> {
+    let greedy = crate::solver::greedy::solve(problem)?;
+    let start: Vec<bool> = greedy.decision.iter().map(|&d| d != T::ZERO).collect();
+    let relaxed = Problem::new(problem.items.clone(), problem.capacity);
+    let upper_bound = minknap::solve(&relaxed)?.value;
+    if verbose {
+        println!(
+            "greedy value: {}, upper bound without precedences: {}",
+            greedy.value, upper_bound
+        );
+    }
+
+    if greedy.value >= upper_bound {
+        return Ok((start, None));
+    }
+    let item_count = problem.items.len();
+    if item_count > EXACT_ITEM_LIMIT {
+        return Err(SolveError::TooLarge {
+            size: item_count,
+            limit: EXACT_ITEM_LIMIT,
+        });
+    }
+    let mut positions = vec![0; item_count];
+    for (position, &index) in precedences.order.iter().enumerate() {
+        positions[index] = position;
+    }
+    let mut efficiency_order: Vec<usize> = (0..item_count).collect();
+    efficiency_order.sort_by(|&a, &b| problem.items[b].efficiency_cmp(&problem.items[a]));
+
+    let mut search = BranchAndBound {
+        problem,
+        precedences,
+        positions,
+        efficiency_order,
+        taken: vec![false; item_count],
+        value: T::ZERO,
+        weight: T::ZERO,
+        best_decision: start,
+        best_value: greedy.value,
+        upper_bound,
+        nodes: 0,
+    };
+    search.search();
+    if verbose {
+        println!("branch and bound nodes: {}", search.nodes);
+    }
+    Ok((search.best_decision, Some(search.nodes)))
+}
+
+/// Dynamic programming over precedence forests, branch and bound otherwise
+pub struct Precedence;
+
+impl<T: Integer> KnapsackSolver<T> for Precedence {
+    fn name(&self) -> &'static str {
+        "Precedence"
+    }
+
+    fn solve(
+        &self,
+        problem: &Problem<T>,
+        config: &SolveConfig,
+    ) -> Result<SolveReport<T>, SolveError> {
+        problem.check_supported("Precedence", &[Extension::Precedences])?;
+        if problem.fixings.is_some() {
+            return solve_fixed(self, problem, config);
+        }
+        problem.check_overflow()?;
+
+        let precedences = Precedences::new(problem)?;
+        let forest = if precedences.is_forest() {
+            if config.verbose {
+                println!("precedences form a forest");
+            }
+            match solve_forest(problem, &precedences, config.verbose) {
+                Ok(decision) => Some(decision),
+                Err(SolveError::TooLarge { .. }) => {
+                    if config.verbose {
+                        println!("capacity too large for the forest table, using branch and bound");
+                    }
+                    None
+                }
+                Err(e) => return Err(e),
+            }
+        } else {
+            None
+        };
+        let (decision, states_explored) = match forest {
+            Some(decision) => (decision, None),
+            None => solve_dag(problem, &precedences, config.verbose)?,
+        };
+
+        let (value, weight) = decision
+            .iter()
+            .zip(&problem.items)
+            .filter(|(&taken, _)| taken)
+            .fold((T::ZERO, T::ZERO), |(value, weight), (_, item)| {
+                (value + item.value, weight + item.weight)
+            });
+        Ok(SolveReport {
+            solution: Solution::from_bools(&decision, value, weight),
+            states_explored,
+            bytes_used: None,
+        })
+    }
+}
+
+pub fn solve<T: Integer>(problem: &Problem<T>) -> Result<Solution<T>, SolveError> {
+    let report = Precedence.solve(problem, &SolveConfig::default())?;
+    Ok(report.solution)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::solver::test_util::{subsets, Lcg};
+
+    /// Pseudo random problem, where each item has a chance of following
+    /// each of up to `parents` earlier items
+    fn example_problem(item_count: usize, parents: usize, seed: u64) -> Problem<u64> {
+        let mut rng = Lcg::new(seed);
+        let items = rng.items(item_count, 0..50, 1..31);
+        let mut problem = Problem::new(items, 6 * item_count as u64);
+        for after in 1..item_count {
+            for _ in 0..parents {
+                if rng.below(3) != 0 {
+                    let before = rng.below(after as u64) as usize;
+                    problem.precedences.push((before, after));
+                }
+            }
+        }
+        problem
+    }
+
+    fn brute_force(problem: &Problem<u64>) -> u64 {
+        subsets(problem)
+            .filter(|(taken, _, weight)| {
+                *weight <= problem.capacity
+                    && problem
+                        .precedences
+                        .iter()
+                        .all(|&(a, b)| taken[a] || !taken[b])
+            })
+            .map(|(_, value, _)| value)
+            .max()
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        for seed in 0..20 {
+            // One parent makes a forest, more make a DAG
+            let problem = example_problem(14, 1 + seed as usize % 3, seed);
+            let solution = solve(&problem).unwrap();
+            assert!(solution.validate(&problem));
+            assert_eq!(solution.value, brute_force(&problem), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn greedy_repairs_precedences() {
+        for seed in 0..20 {
+            let problem = example_problem(30, 2, seed);
+            let solution = crate::solver::greedy::solve(&problem).unwrap();
+            assert!(solution.validate(&problem), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn forests_too_large_for_the_table() {
+        let items = vec![
+            Item::new(0, 10u64, 3_000_000_000),
+            Item::new(1, 25, 2_000_000_000),
+            Item::new(2, 20, 2_500_000_000),
+        ];
+        let mut problem = Problem::new(items, 5_000_000_000);
+        problem.precedences = vec![(0, 1)];
+        let solution = solve(&problem).unwrap();
+        assert!(solution.validate(&problem));
+        assert_eq!(solution.value, 35);
+    }
+
+    /// Each item follows the two before it, so only prefixes of the items can be taken
+    fn chain_problem(item_count: usize) -> Problem<u64> {
+        let mut problem = example_problem(item_count, 0, 5);
+        for after in 1..item_count {
+            problem.precedences.push((after - 1, after));
+            if after > 1 {
+                problem.precedences.push((after - 2, after));
+            }
+        }
+        problem
+    }
+
+    #[test]
+    fn long_chains() {
+        let problem = chain_problem(EXACT_ITEM_LIMIT);
+        let best_prefix = problem
+            .items
+            .iter()
+            .scan((0, 0), |(value, weight), item| {
+                *value += item.value;
+                *weight += item.weight;
+                Some((*value, *weight))
+            })
+            .filter(|&(_, weight)| weight <= problem.capacity)
+            .map(|(value, _)| value)
+            .max()
+            .unwrap_or(0);
+        let solution = solve(&problem).unwrap();
+        assert!(solution.validate(&problem));
+        assert_eq!(solution.value, best_prefix);
+
+        // Past the limit the search is not run
+        let problem = chain_problem(100 * EXACT_ITEM_LIMIT);
+        assert!(matches!(
+            solve(&problem),
+            Err(SolveError::TooLarge {
+                size,
+                limit: EXACT_ITEM_LIMIT,
+            }) if size == 100 * EXACT_ITEM_LIMIT
+        ));
+    }
+
+    #[test]
+    fn cycles_are_rejected() {
+        let items = (0..4).map(|id| Item::new(id, 10u32, 1)).collect();
+        let mut problem = Problem::new(items, 10);
+        problem.precedences = vec![(0, 1), (1, 2), (2, 3), (3, 1)];
+        match solve(&problem) {
+            Err(SolveError::InvalidProblem(reason)) => assert!(reason.contains("1, 2, 3")),
+            other => panic!("expected a cycle, got {:?}", other),
+        }
+        let solution = Solution::from_bools(&[false; 4], 0, 0);
+        assert!(!solution.validate(&problem));
+    }
+}
@@ -1,62 +1,1008 @@
-#[derive(Copy, Clone)]
-pub struct Item {
+use crate::solver::integer::*;
+use crate::solver::parse::*;
+use crate::solver::registry::SolveError;
+use std::collections::BTreeSet;
+
+#[derive(Debug, Copy, Clone)]
+pub struct Item<T = u64> {
     pub id: usize,
-    pub value: usize,
-    pub weight: usize,
+    pub value: T,
+    pub weight: T,
+    /// For multiple-choice problems, the class the item belongs to.
+    /// Exactly one item of each class is chosen
+    pub class: Option<usize>,
 }
 
-impl Item {
-    fn new(id: usize, value: usize, weight: usize) -> Item {
-        Item { id, value, weight }
+impl<T: Integer> Item<T> {
+    pub fn new(id: usize, value: T, weight: T) -> Item<T> {
+        Item {
+            id,
+            value,
+            weight,
+            class: None,
+        }
+    }
+
+    /// Exactly compare the value / weight ratio of two items,
+    /// zero weight items are more efficient than any other item
+    pub fn efficiency_cmp(&self, other: &Item<T>) -> std::cmp::Ordering {
+        use std::cmp::Ordering;
+        match (self.weight == T::ZERO, other.weight == T::ZERO) {
+            (true, true) => self.value.cmp(&other.value),
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => cmp_products(self.value, other.weight, other.value, self.weight),
+        }
     }
 }
 
-pub struct Problem {
-    pub items: Vec<Item>,
-    pub capacity: usize,
+/// Optional parts of a problem, beyond the plain 0-1 knapsack problem
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Extension {
+    /// Items may be taken more than once, see `Problem::copies`
+    Copies,
+    /// Items belong to classes, see `Item::class`
+    Classes,
+    /// Capacity constraints beyond the first, see `Problem::dimensions`
+    Dimensions,
+    /// Several knapsacks to pack items into, see `Problem::bins`
+    Bins,
+    /// Items cover a weight instead of fitting in a capacity, see `Problem::covering`
+    Covering,
+    /// Limits on the number of items taken, see `Problem::cardinality`
+    Cardinality,
+    /// Pairs of items that can not both be taken, see `Problem::conflicts`
+    Conflicts,
+    /// Items that can only be taken along with others, see `Problem::precedences`
+    Precedences,
+    /// A second value to trade off against the first, see `Problem::second_values`
+    SecondValues,
+    /// Weights that may deviate from their nominal value, see `Problem::uncertainty`
+    Uncertainty,
 }
 
-impl Problem {
-    pub fn read<F: std::io::BufRead>(input: F) -> Result<Problem, Box<dyn std::error::Error>> {
-        let mut lines = input.lines();
+impl std::fmt::Display for Extension {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Extension::Copies => write!(f, "item copy counts"),
+            Extension::Classes => write!(f, "item classes"),
+            Extension::Dimensions => write!(f, "several capacity constraints"),
+            Extension::Bins => write!(f, "several knapsacks"),
+            Extension::Covering => write!(f, "covering problems"),
+            Extension::Cardinality => write!(f, "limits on the number of items"),
+            Extension::Conflicts => write!(f, "conflicts between items"),
+            Extension::Precedences => write!(f, "precedence constraints"),
+            Extension::SecondValues => write!(f, "a second value for each item"),
+            Extension::Uncertainty => write!(f, "uncertain weights"),
+        }
+    }
+}
 
-        let item_count = match lines.next() {
-            Some(token) => token?.parse::<usize>()?,
-            None => panic!("Should be usize for item count"),
-        };
+/// A capacity constraint beyond the first, for multidimensional problems
+#[derive(Debug, Clone)]
+pub struct Dimension<T = u64> {
+    pub capacity: T,
+    /// Weight of each item in this dimension, in the same order as `Problem::items`
+    pub weights: Vec<T>,
+}
+
+/// Fewest and most items a solution may take, counting every copy
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CountLimits {
+    pub min: usize,
+    pub max: usize,
+}
+
+impl CountLimits {
+    pub fn contains(&self, count: usize) -> bool {
+        self.min <= count && count <= self.max
+    }
+}
+
+/// Whether an item is left for the solver to decide, see `Problem::fixings`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Fixing {
+    Free,
+    /// The item must be taken at least once
+    In,
+    /// The item must be left out
+    Out,
+}
+
+/// Budgeted uncertainty in the item weights, as Bertsimas and Sim define it.
+/// Each item may weigh up to its deviation more than `Item::weight`,
+/// but no more than `gamma` items deviate at once
+#[derive(Debug, Clone)]
+pub struct Uncertainty<T = u64> {
+    /// Most extra weight of each item, in the same order as `Problem::items`
+    pub deviations: Vec<T>,
+    pub gamma: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Problem<T = u64> {
+    pub items: Vec<Item<T>>,
+    pub capacity: T,
+    /// How many copies of each item are available, None if each item can be taken once.
+    /// A count of `T::MAX` stands for an unlimited supply
+    pub copies: Option<Vec<T>>,
+    /// Constraints every solution must also meet, on top of `capacity` and `Item::weight`.
+    /// Empty unless the problem is multidimensional
+    pub dimensions: Vec<Dimension<T>>,
+    /// For multiple knapsack problems, capacities of the knapsacks beyond the first.
+    /// Each item goes in at most one knapsack, the first of which has `capacity`
+    pub bins: Vec<T>,
+    /// For covering problems, the items taken must weigh at least `capacity`,
+    /// and the solution with the least total value is best
+    pub covering: bool,
+    /// Limits on how many items are taken, None if any number may be
+    pub cardinality: Option<CountLimits>,
+    /// Indices of pairs of items that can not both be taken, the edges of the conflict graph
+    pub conflicts: Vec<(usize, usize)>,
+    /// Indices of pairs of items where the second item can only be taken
+    /// if the first one is too. Must not form a cycle
+    pub precedences: Vec<(usize, usize)>,
+    /// For bi-objective problems, the second value of each item
+    pub second_values: Option<Vec<T>>,
+    /// For robust problems, how far the item weights may deviate.
+    /// Solutions must fit whichever items deviate
+    pub uncertainty: Option<Uncertainty<T>>,
+    /// Decisions made ahead of the solver for each item, None if every item is free
+    pub fixings: Option<Vec<Fixing>>,
+}
+
+impl<T: Integer> Problem<T> {
+    pub fn new(items: Vec<Item<T>>, capacity: T) -> Problem<T> {
+        Problem {
+            items,
+            capacity,
+            copies: None,
+            dimensions: Vec::new(),
+            bins: Vec::new(),
+            covering: false,
+            cardinality: None,
+            conflicts: Vec::new(),
+            precedences: Vec::new(),
+            second_values: None,
+            uncertainty: None,
+            fixings: None,
+        }
+    }
+
+    /// Read a problem in the format
+    /// ```text
+    /// <item count>
+    /// <id> <value> <weight>
+    /// ...
+    /// <capacity>
+    /// ```
+    /// Blank lines are ignored
+    ///
+    /// Extensions to the plain problem are given by keyword lines after the capacity
+    /// ```text
+    /// copies <id> <count>
+    /// class <id> <class>
+    /// dimension <capacity>
+    /// weights <id> <weight> ...
+    /// bins <capacity> ...
+    /// covering
+    /// cardinality <min> <max>
+    /// conflict <id> <id>
+    /// precedence <id> <id>
+    /// second_value <id> <value>
+    /// deviation <id> <weight>
+    /// gamma <count>
+    /// fix <id> in|out
+    /// ```
+    /// Each `dimension` line adds a capacity constraint, and `weights` lines give an item's
+    /// weight in every dimension added above them. Items default to no weight in a dimension.
+    /// `bins` lines add knapsacks with the listed capacities, alongside the first one.
+    /// A `covering` line makes the capacity a weight to reach at the least value,
+    /// and a `cardinality` line limits the number of items taken.
+    /// A `conflict` line keeps two items from both being taken,
+    /// and a `precedence` line only lets the second item be taken along with the first.
+    /// `second_value` lines give items a second value, which defaults to 0.
+    /// `deviation` lines let an item weigh more than its nominal weight, by up to 0 if not
+    /// given, and a `gamma` line limits how many items deviate at once, every item if not given.
+    /// `fix` lines force an item into or out of every solution
+    pub fn read<F: std::io::BufRead>(input: F) -> Result<Problem<T>, ParseError> {
+        let mut reader = LineReader::new(input);
+
+        let mut count_line = reader.expect_line("item count")?;
+        let item_count = count_line.count_token("item count")?;
+        count_line.finish()?;
 
         let mut items = Vec::with_capacity(item_count);
-        for _ in 0..item_count {
-            if let Some(Ok(tokens_line)) = lines.next() {
-                let mut tokens = tokens_line.split_whitespace();
-                let id = tokens.next().unwrap().parse::<usize>()?;
-                let value = tokens.next().unwrap().parse::<usize>()?;
-                let weight = tokens.next().unwrap().parse::<usize>()?;
-                items.push(Item::new(id, value, weight));
-            } else {
-                panic!("Should be usize for item count");
+        for found in 0..item_count {
+            let count_mismatch = ParseErrorCause::CountMismatch {
+                expected: item_count,
+                found,
+            };
+            let mut line = match reader.next_line()? {
+                Some(line) => line,
+                None => return Err(ParseError::new(reader.end_line(), 1, count_mismatch)),
+            };
+
+            // A lone number on the last line is the capacity,
+            // so the item list came up short
+            if line.token_count() == 1 && reader.at_end() {
+                return Err(line.error(count_mismatch));
+            }
+
+            let id = line.count_token("item id")?;
+            let value = line.number_token("item value")?;
+            let weight = line.number_token("item weight")?;
+            line.finish()?;
+            items.push(Item::new(id, value, weight));
+        }
+
+        let mut capacity_line = reader.expect_line("capacity")?;
+        let capacity = capacity_line.number_token("capacity")?;
+        capacity_line.finish()?;
+
+        let mut problem = Problem::new(items, capacity);
+        let ids = IdIndex::new(problem.items.iter().map(|item| item.id));
+        while let Some(mut line) = reader.next_line()? {
+            match line.keyword_token(&[
+                "copies",
+                "class",
+                "dimension",
+                "weights",
+                "bins",
+                "covering",
+                "cardinality",
+                "conflict",
+                "precedence",
+                "second_value",
+                "deviation",
+                "gamma",
+                "fix",
+            ])? {
+                "copies" => {
+                    let index = ids.item_token(&mut line, "item id")?;
+                    let count = line.number_token("copy count")?;
+                    let item_count = problem.items.len();
+                    let copies = problem
+                        .copies
+                        .get_or_insert_with(|| vec![T::ONE; item_count]);
+                    copies[index] = count;
+                }
+                "class" => {
+                    let index = ids.item_token(&mut line, "item id")?;
+                    problem.items[index].class = Some(line.count_token("class")?);
+                }
+                "dimension" => {
+                    let capacity = line.number_token("capacity")?;
+                    let weights = vec![T::ZERO; problem.items.len()];
+                    problem.dimensions.push(Dimension { capacity, weights });
+                }
+                "weights" => {
+                    let index = ids.item_token(&mut line, "item id")?;
+                    for dimension in problem.dimensions.iter_mut() {
+                        dimension.weights[index] = line.number_token("item weight")?;
+                    }
+                }
+                "bins" => {
+                    problem.bins.push(line.number_token("capacity")?);
+                    while line.has_tokens_left() {
+                        problem.bins.push(line.number_token("capacity")?);
+                    }
+                }
+                "covering" => problem.covering = true,
+                "cardinality" => {
+                    let min = line.count_token("least item count")?;
+                    let max = line.count_token("most item count")?;
+                    problem.cardinality = Some(CountLimits { min, max });
+                }
+                "conflict" => {
+                    let first = ids.item_token(&mut line, "item id")?;
+                    let second = ids.item_token(&mut line, "item id")?;
+                    problem.conflicts.push((first, second));
+                }
+                "precedence" => {
+                    let before = ids.item_token(&mut line, "item id")?;
+                    let after = ids.item_token(&mut line, "item id")?;
+                    problem.precedences.push((before, after));
+                }
+                "second_value" => {
+                    let index = ids.item_token(&mut line, "item id")?;
+                    let value = line.number_token("second value")?;
+                    let item_count = problem.items.len();
+                    let second_values = problem
+                        .second_values
+                        .get_or_insert_with(|| vec![T::ZERO; item_count]);
+                    second_values[index] = value;
+                }
+                "deviation" => {
+                    let index = ids.item_token(&mut line, "item id")?;
+                    let deviation = line.number_token("weight deviation")?;
+                    problem.uncertainty_mut().deviations[index] = deviation;
+                }
+                "gamma" => {
+                    let gamma = line.count_token("deviating item count")?;
+                    problem.uncertainty_mut().gamma = gamma;
+                }
+                "fix" => {
+                    let index = ids.item_token(&mut line, "item id")?;
+                    let fixing = match line.keyword_token(&["in", "out"])? {
+                        "in" => Fixing::In,
+                        _ => Fixing::Out,
+                    };
+                    problem.fix(index, fixing);
+                }
+                _ => unreachable!(),
+            }
+            line.finish()?;
+        }
+
+        Ok(problem)
+    }
+
+    /// Number of copies available of the item at `index`
+    pub fn copies(&self, index: usize) -> T {
+        match &self.copies {
+            Some(copies) => copies[index],
+            None => T::ONE,
+        }
+    }
+
+    /// Copies of the item at `index` that could ever be taken,
+    /// at most as many as fit in the capacity
+    pub fn usable_copies(&self, index: usize) -> T {
+        let copies = self.copies(index);
+        let weight = self.items[index].weight;
+        if weight == T::ZERO {
+            copies
+        } else {
+            copies.min(self.capacity / weight)
+        }
+    }
+
+    /// Second value of the item at `index`, 0 unless the problem has second values
+    pub fn second_value(&self, index: usize) -> T {
+        match &self.second_values {
+            Some(second_values) => second_values[index],
+            None => T::ZERO,
+        }
+    }
+
+    /// The problem's uncertainty, adding one where no item deviates yet
+    pub fn uncertainty_mut(&mut self) -> &mut Uncertainty<T> {
+        let item_count = self.items.len();
+        self.uncertainty.get_or_insert_with(|| Uncertainty {
+            deviations: vec![T::ZERO; item_count],
+            gamma: item_count,
+        })
+    }
+
+    /// Most a decision can weigh, when the `gamma` items taken with the largest
+    /// deviations all deviate. None if the weight overflows
+    pub fn robust_weight(&self, decision: &[T]) -> Option<T> {
+        let mut weight = decision
+            .iter()
+            .zip(&self.items)
+            .try_fold(T::ZERO, |sum, (&d, item)| {
+                item.weight.checked_mul(d).and_then(|w| sum.checked_add(w))
+            })?;
+        if let Some(uncertainty) = &self.uncertainty {
+            let mut deviating: Vec<(T, T)> = decision
+                .iter()
+                .zip(&uncertainty.deviations)
+                .filter(|(&d, _)| d != T::ZERO)
+                .map(|(&d, &deviation)| (deviation, d))
+                .collect();
+            deviating.sort_unstable_by_key(|&(deviation, _)| std::cmp::Reverse(deviation));
+            // Each copy taken may deviate on its own
+            let mut left = T::from_usize(uncertainty.gamma).unwrap_or(T::MAX);
+            for (deviation, count) in deviating {
+                let count = count.min(left);
+                weight = deviation
+                    .checked_mul(count)
+                    .and_then(|extra| weight.checked_add(extra))?;
+                left -= count;
+            }
+        }
+        Some(weight)
+    }
+
+    /// How the item at `index` is fixed
+    pub fn fixing(&self, index: usize) -> Fixing {
+        match &self.fixings {
+            Some(fixings) => fixings[index],
+            None => Fixing::Free,
+        }
+    }
+
+    /// Force the item at `index` into or out of every solution
+    pub fn fix(&mut self, index: usize, fixing: Fixing) {
+        let item_count = self.items.len();
+        let fixings = self
+            .fixings
+            .get_or_insert_with(|| vec![Fixing::Free; item_count]);
+        fixings[index] = fixing;
+    }
+
+    /// Capacity of every knapsack, just `capacity` unless the problem has several bins
+    pub fn bin_capacities(&self) -> Vec<T> {
+        std::iter::once(self.capacity)
+            .chain(self.bins.iter().copied())
+            .collect()
+    }
+
+    /// Allow every item to be taken any number of times
+    pub fn make_unbounded(&mut self) {
+        self.copies = Some(vec![T::MAX; self.items.len()]);
+    }
+
+    /// True if every item can be taken any number of times
+    pub fn is_unbounded(&self) -> bool {
+        match &self.copies {
+            Some(copies) => copies.iter().all(|&c| c == T::MAX),
+            None => false,
+        }
+    }
+
+    /// True if every item is worth exactly its weight
+    pub fn is_subset_sum(&self) -> bool {
+        self.items.iter().all(|item| item.value == item.weight)
+    }
+
+    /// Which extensions to the plain 0-1 problem this problem uses
+    pub fn extensions(&self) -> Vec<Extension> {
+        let mut extensions = Vec::new();
+        if self.copies.is_some() {
+            extensions.push(Extension::Copies);
+        }
+        if self.items.iter().any(|item| item.class.is_some()) {
+            extensions.push(Extension::Classes);
+        }
+        if !self.dimensions.is_empty() {
+            extensions.push(Extension::Dimensions);
+        }
+        if !self.bins.is_empty() {
+            extensions.push(Extension::Bins);
+        }
+        if self.covering {
+            extensions.push(Extension::Covering);
+        }
+        if self.cardinality.is_some() {
+            extensions.push(Extension::Cardinality);
+        }
+        if !self.conflicts.is_empty() {
+            extensions.push(Extension::Conflicts);
+        }
+        if !self.precedences.is_empty() {
+            extensions.push(Extension::Precedences);
+        }
+        if self.second_values.is_some() {
+            extensions.push(Extension::SecondValues);
+        }
+        if self.uncertainty.is_some() {
+            extensions.push(Extension::Uncertainty);
+        }
+        extensions
+    }
+
+    /// Error unless every extension the problem uses is `supported` by the solver
+    pub fn check_supported(
+        &self,
+        solver: &'static str,
+        supported: &[Extension],
+    ) -> Result<(), SolveError> {
+        match self
+            .extensions()
+            .into_iter()
+            .find(|e| !supported.contains(e))
+        {
+            Some(extension) => Err(SolveError::Unsupported { solver, extension }),
+            None => Ok(()),
+        }
+    }
+
+    /// For a 0-1 covering problem, the packing problem of choosing which items to leave out.
+    /// The items left out may weigh at most the total weight less the weight to cover,
+    /// and leaving out the most value leaves the cheapest cover.
+    /// See `Solution::complement` to turn its solutions back into covers
+    pub fn complement(&self) -> Result<Problem<T>, SolveError> {
+        // Problem::check_overflow guarantees this fits
+        let total_weight: T = self.items.iter().map(|item| item.weight).sum();
+        if total_weight < self.capacity {
+            return Err(SolveError::Infeasible(format!(
+                "every item together weighs {}, less than the {} to cover",
+                total_weight, self.capacity
+            )));
+        }
+        let mut packing = Problem::new(self.items.clone(), total_weight - self.capacity);
+        // Items fixed into the cover are fixed out of the items left out, and the reverse
+        packing.fixings = self.fixings.as_ref().map(|fixings| {
+            fixings
+                .iter()
+                .map(|fixing| match fixing {
+                    Fixing::Free => Fixing::Free,
+                    Fixing::In => Fixing::Out,
+                    Fixing::Out => Fixing::In,
+                })
+                .collect()
+        });
+        // An item can only be left out of the cover if every item it precedes is too
+        packing.precedences = self
+            .precedences
+            .iter()
+            .map(|&(before, after)| (after, before))
+            .collect();
+        Ok(packing)
+    }
+
+    /// Every item, ordered so that each item comes after the items that precede it.
+    /// Precedences that form a cycle are reported as an invalid problem
+    pub fn precedence_order(&self) -> Result<Vec<usize>, SolveError> {
+        let item_count = self.items.len();
+        let mut successors = vec![Vec::new(); item_count];
+        let mut waiting = vec![0; item_count];
+        for &(before, after) in &self.precedences {
+            successors[before].push(after);
+            waiting[after] += 1;
+        }
+
+        let mut order: Vec<usize> = (0..item_count).filter(|&i| waiting[i] == 0).collect();
+        let mut next = 0;
+        while let Some(&index) = order.get(next) {
+            next += 1;
+            for &after in &successors[index] {
+                waiting[after] -= 1;
+                if waiting[after] == 0 {
+                    order.push(after);
+                }
             }
         }
+        if order.len() == item_count {
+            return Ok(order);
+        }
+
+        // Every item left waits on another item left, so following those
+        // backwards from any of them runs into a cycle
+        let mut predecessor = vec![None; item_count];
+        for &(before, after) in &self.precedences {
+            if waiting[before] != 0 {
+                predecessor[after] = Some(before);
+            }
+        }
+        let mut path = Vec::new();
+        let mut index = (0..item_count).find(|&i| waiting[i] != 0).unwrap();
+        while !path.contains(&index) {
+            path.push(index);
+            index = predecessor[index].unwrap();
+        }
+        let start = path.iter().position(|&i| i == index).unwrap();
+        // The path runs backwards, from each item to the one before it
+        let ids: Vec<String> = std::iter::once(&path[start])
+            .chain(path[start + 1..].iter().rev())
+            .map(|&i| self.items[i].id.to_string())
+            .collect();
+        Err(SolveError::InvalidProblem(format!(
+            "the precedences of items {} form a cycle",
+            ids.join(", ")
+        )))
+    }
 
-        let capacity = match lines.next() {
-            Some(token) => token?.parse::<usize>()?,
-            None => panic!("Should be usize for capacity"),
+    /// Extend the fixings to every item they decide. Items fixed in fix the items that
+    /// precede them in, and the rest of their class and the items they conflict with out.
+    /// Items fixed out fix the items they precede out
+    pub fn close_fixings(&mut self) -> Result<(), SolveError> {
+        let item_count = self.items.len();
+        // What fixing each item in, or out, forces on other items
+        let mut implied_in = vec![Vec::new(); item_count];
+        let mut implied_out = vec![Vec::new(); item_count];
+        for &(before, after) in &self.precedences {
+            implied_in[after].push((before, Fixing::In));
+            implied_out[before].push((after, Fixing::Out));
+        }
+        for &(first, second) in &self.conflicts {
+            implied_in[first].push((second, Fixing::Out));
+            implied_in[second].push((first, Fixing::Out));
+        }
+        let mut classes = std::collections::BTreeMap::new();
+        for (index, item) in self.items.iter().enumerate() {
+            if let Some(class) = item.class {
+                classes.entry(class).or_insert_with(Vec::new).push(index);
+            }
+        }
+
+        let mut stack: Vec<usize> = (0..item_count)
+            .filter(|&index| self.fixing(index) != Fixing::Free)
+            .collect();
+        while let Some(index) = stack.pop() {
+            let fixing = self.fixing(index);
+            let mut forced = match fixing {
+                Fixing::In => implied_in[index].clone(),
+                _ => implied_out[index].clone(),
+            };
+            if let (Fixing::In, Some(class)) = (fixing, self.items[index].class) {
+                forced.extend(
+                    classes[&class]
+                        .iter()
+                        .filter(|&&other| other != index)
+                        .map(|&other| (other, Fixing::Out)),
+                );
+            }
+            for (other, forced) in forced {
+                match self.fixing(other) {
+                    Fixing::Free => {
+                        self.fix(other, forced);
+                        stack.push(other);
+                    }
+                    current if current != forced => {
+                        return Err(SolveError::Infeasible(format!(
+                            "item {} is fixed both in and out",
+                            self.items[other].id
+                        )))
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Indices of the items a solver still decides on once the fixings are applied.
+    /// Items fixed in stay only while they have copies to spare, classes with an item
+    /// fixed in are decided, and items in conflict with an item fixed in are left out
+    fn free_indices(&self) -> Vec<usize> {
+        let fixed_classes: BTreeSet<usize> = (0..self.items.len())
+            .filter(|&index| self.fixing(index) == Fixing::In)
+            .filter_map(|index| self.items[index].class)
+            .collect();
+        let mut blocked = BTreeSet::new();
+        for &(first, second) in &self.conflicts {
+            if self.fixing(first) == Fixing::In {
+                blocked.insert(second);
+            }
+            if self.fixing(second) == Fixing::In {
+                blocked.insert(first);
+            }
+        }
+        (0..self.items.len())
+            .filter(|&index| {
+                let item = &self.items[index];
+                match self.fixing(index) {
+                    Fixing::Out => false,
+                    Fixing::In => item.class.is_none() && self.copies(index) > T::ONE,
+                    Fixing::Free => {
+                        item.class.is_none_or(|c| !fixed_classes.contains(&c))
+                            && !blocked.contains(&index)
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// The problem left for the free items once the fixings are applied.
+    /// Items fixed out are dropped, and one copy of each item fixed in is taken out of
+    /// the capacities, the item count limits and the item's class.
+    /// See `Solution::with_fixings` to add the fixed items back to its solutions
+    pub fn without_fixings(&self) -> Result<Problem<T>, SolveError> {
+        // Problem::check_overflow guarantees these sums fit
+        let mut fixed_weight = T::ZERO;
+        let mut dimension_weights = vec![T::ZERO; self.dimensions.len()];
+        let mut fixed_count = 0;
+        let mut fixed_classes = BTreeSet::new();
+        let mut open_classes = BTreeSet::new();
+        for (index, item) in self.items.iter().enumerate() {
+            match self.fixing(index) {
+                Fixing::Out => continue,
+                Fixing::Free => {
+                    open_classes.extend(item.class);
+                    continue;
+                }
+                Fixing::In => {}
+            }
+            if !self.bins.is_empty() {
+                return Err(SolveError::InvalidProblem(format!(
+                    "item {} is fixed in, with several knapsacks items may only be fixed out",
+                    item.id
+                )));
+            }
+            if let Some(class) = item.class {
+                if !fixed_classes.insert(class) {
+                    return Err(SolveError::Infeasible(format!(
+                        "more than one item of class {} is fixed in",
+                        class
+                    )));
+                }
+                open_classes.insert(class);
+            }
+            fixed_weight += item.weight;
+            for (sum, dimension) in dimension_weights.iter_mut().zip(&self.dimensions) {
+                *sum += dimension.weights[index];
+            }
+            fixed_count += 1;
+        }
+        // Items fixed in may deviate too, which no reduced capacity accounts for
+        if fixed_count > 0 && self.uncertainty.is_some() {
+            return Err(SolveError::InvalidProblem(
+                "items with uncertain weights can not be fixed in ahead of the solver".to_string(),
+            ));
+        }
+
+        if let Some(&(first, second)) = self.conflicts.iter().find(|&&(first, second)| {
+            self.fixing(first) == Fixing::In && self.fixing(second) == Fixing::In
+        }) {
+            return Err(SolveError::Infeasible(format!(
+                "items {} and {} are fixed in, but conflict",
+                self.items[first].id, self.items[second].id
+            )));
+        }
+
+        if let Some(item) = self
+            .items
+            .iter()
+            .find(|item| item.class.is_some_and(|c| !open_classes.contains(&c)))
+        {
+            return Err(SolveError::Infeasible(format!(
+                "every item of class {} is fixed out",
+                item.class.unwrap()
+            )));
+        }
+
+        let capacity = if self.covering {
+            self.capacity.saturating_sub(fixed_weight)
+        } else if fixed_weight <= self.capacity {
+            self.capacity - fixed_weight
+        } else {
+            return Err(SolveError::Infeasible(format!(
+                "the items fixed in weigh {}, over the capacity of {}",
+                fixed_weight, self.capacity
+            )));
+        };
+
+        let cardinality = match self.cardinality {
+            Some(limits) if fixed_count > limits.max => {
+                return Err(SolveError::Infeasible(format!(
+                    "{} items are fixed in, but at most {} may be taken",
+                    fixed_count, limits.max
+                )))
+            }
+            Some(limits) => Some(CountLimits {
+                min: limits.min.saturating_sub(fixed_count),
+                max: limits.max - fixed_count,
+            }),
+            None => None,
         };
 
-        Ok(Problem { items, capacity })
+        let free = self.free_indices();
+        let mut dimensions = Vec::with_capacity(self.dimensions.len());
+        for (number, (dimension, &weight)) in
+            self.dimensions.iter().zip(&dimension_weights).enumerate()
+        {
+            if weight > dimension.capacity {
+                return Err(SolveError::Infeasible(format!(
+                    "the items fixed in weigh {} in dimension {}, over its capacity of {}",
+                    weight,
+                    number + 2,
+                    dimension.capacity
+                )));
+            }
+            dimensions.push(Dimension {
+                capacity: dimension.capacity - weight,
+                weights: free.iter().map(|&index| dimension.weights[index]).collect(),
+            });
+        }
+
+        let copies = self.copies.as_ref().map(|_| {
+            free.iter()
+                .map(|&index| {
+                    let copies = self.copies(index);
+                    if self.fixing(index) == Fixing::In && copies != T::MAX {
+                        copies - T::ONE
+                    } else {
+                        copies
+                    }
+                })
+                .collect()
+        });
+
+        let mut positions = vec![None; self.items.len()];
+        for (position, &index) in free.iter().enumerate() {
+            positions[index] = Some(position);
+        }
+        let conflicts = self
+            .conflicts
+            .iter()
+            .filter_map(|&(first, second)| Some((positions[first]?, positions[second]?)))
+            .collect();
+        // Precedences with an item fixed in either hold already, or are broken,
+        // which `Problem::close_fixings` finds
+        let precedences = self
+            .precedences
+            .iter()
+            .filter(|&&(before, after)| {
+                self.fixing(before) == Fixing::Free && self.fixing(after) == Fixing::Free
+            })
+            .filter_map(|&(before, after)| Some((positions[before]?, positions[after]?)))
+            .collect();
+
+        Ok(Problem {
+            items: free.iter().map(|&index| self.items[index]).collect(),
+            capacity,
+            copies,
+            dimensions,
+            bins: self.bins.clone(),
+            covering: self.covering,
+            cardinality,
+            conflicts,
+            precedences,
+            second_values: self
+                .second_values
+                .as_ref()
+                .map(|values| free.iter().map(|&index| values[index]).collect()),
+            uncertainty: self.uncertainty.as_ref().map(|uncertainty| Uncertainty {
+                deviations: free
+                    .iter()
+                    .map(|&index| uncertainty.deviations[index])
+                    .collect(),
+                gamma: uncertainty.gamma,
+            }),
+            fixings: None,
+        })
+    }
+
+    /// Check that every sum a solver might compute fits in `T`.
+    /// Solvers call this before solving, so their inner loops can use plain arithmetic
+    ///
+    /// Profits never exceed the sum of all values, and states never weigh more
+    /// than the capacity plus the sum of all weights, counting every copy of an item.
+    /// For multidimensional problems the capacities and weights of every dimension are
+    /// summed together, as the surrogate relaxation of the `Multidim` solver does,
+    /// and the capacities of every bin are added to the capacity.
+    /// Second values are summed on their own, like values.
+    /// Unlimited copies of a weightless item with value are reported as an invalid problem
+    pub fn check_overflow(&self) -> Result<(), SolveError> {
+        let mut value_sum = T::ZERO;
+        let mut weight_sum = self
+            .bins
+            .iter()
+            .try_fold(self.capacity, |sum, &c| sum.checked_add(c))
+            .ok_or(SolveError::Overflow("sum of bin capacities"))?;
+        for (index, item) in self.items.iter().enumerate() {
+            let copies = self.usable_copies(index);
+            if copies == T::MAX && item.weight == T::ZERO && item.value != T::ZERO {
+                return Err(SolveError::InvalidProblem(format!(
+                    "item {} has value, no weight and unlimited copies, so the profit is unbounded",
+                    item.id
+                )));
+            }
+            value_sum = item
+                .value
+                .checked_mul(copies)
+                .and_then(|v| value_sum.checked_add(v))
+                .ok_or(SolveError::Overflow("sum of item values"))?;
+            weight_sum = item
+                .weight
+                .checked_mul(copies)
+                .and_then(|w| weight_sum.checked_add(w))
+                .ok_or(SolveError::Overflow("capacity plus sum of item weights"))?;
+        }
+        for dimension in &self.dimensions {
+            weight_sum = dimension
+                .weights
+                .iter()
+                .try_fold(dimension.capacity, |sum, &w| sum.checked_add(w))
+                .and_then(|w| weight_sum.checked_add(w))
+                .ok_or(SolveError::Overflow(
+                    "sum of capacities and item weights over every dimension",
+                ))?;
+        }
+        if let Some(second_values) = &self.second_values {
+            second_values
+                .iter()
+                .enumerate()
+                .try_fold(T::ZERO, |sum, (index, &value)| {
+                    value
+                        .checked_mul(self.usable_copies(index))
+                        .and_then(|v| sum.checked_add(v))
+                })
+                .ok_or(SolveError::Overflow("sum of second item values"))?;
+        }
+        if let Some(uncertainty) = &self.uncertainty {
+            uncertainty
+                .deviations
+                .iter()
+                .enumerate()
+                .try_fold(weight_sum, |sum, (index, &deviation)| {
+                    deviation
+                        .checked_mul(self.usable_copies(index))
+                        .and_then(|d| sum.checked_add(d))
+                })
+                .ok_or(SolveError::Overflow(
+                    "capacity plus sum of item weights and deviations",
+                ))?;
+        }
+        Ok(())
     }
 }
 
-pub struct Solution {
-    pub decision: Vec<bool>,
-    pub value: usize,
-    pub weight: usize,
+#[derive(Debug)]
+pub struct Solution<T = u64> {
+    /// How many copies of each item are taken,
+    /// always 0 or 1 unless the problem has copy counts
+    pub decision: Vec<T>,
+    pub value: T,
+    pub weight: T,
+    /// For multiple knapsack problems, the index in `Problem::bin_capacities`
+    /// of the bin each item is packed in, None for items left out
+    pub bins: Option<Vec<Option<usize>>>,
 }
 
-impl Solution {
-    pub fn validate(&self, problem: &Problem) -> bool {
-        let mut value_sum = 0;
-        let mut weight_sum = 0;
+impl<T: Integer> Solution<T> {
+    /// Solution for a 0-1 problem, from whether each item is taken
+    pub fn from_bools(decision: &[bool], value: T, weight: T) -> Solution<T> {
+        Solution {
+            decision: decision
+                .iter()
+                .map(|&d| if d { T::ONE } else { T::ZERO })
+                .collect(),
+            value,
+            weight,
+            bins: None,
+        }
+    }
+
+    /// For a 0-1 covering problem, the cover made of the items a solution to
+    /// `Problem::complement` leaves out
+    pub fn complement(&self, problem: &Problem<T>) -> Solution<T> {
+        let mut decision = Vec::with_capacity(self.decision.len());
+        let mut value = T::ZERO;
+        let mut weight = T::ZERO;
+        for (&d, item) in self.decision.iter().zip(&problem.items) {
+            if d == T::ZERO {
+                decision.push(T::ONE);
+                value += item.value;
+                weight += item.weight;
+            } else {
+                decision.push(T::ZERO);
+            }
+        }
+        Solution {
+            decision,
+            value,
+            weight,
+            bins: None,
+        }
+    }
+
+    /// For a problem with fixings, the solution made of a solution to
+    /// `Problem::without_fixings` and the items fixed in
+    pub fn with_fixings(&self, problem: &Problem<T>) -> Solution<T> {
+        let item_count = problem.items.len();
+        let mut decision = vec![T::ZERO; item_count];
+        let mut bins = self.bins.as_ref().map(|_| vec![None; item_count]);
+        for (free, index) in problem.free_indices().into_iter().enumerate() {
+            decision[index] = self.decision[free];
+            if let (Some(bins), Some(free_bins)) = (&mut bins, &self.bins) {
+                bins[index] = free_bins[free];
+            }
+        }
+
+        let mut value = self.value;
+        let mut weight = self.weight;
+        for (index, item) in problem.items.iter().enumerate() {
+            if problem.fixing(index) == Fixing::In {
+                decision[index] += T::ONE;
+                value += item.value;
+                weight += item.weight;
+            }
+        }
+        Solution {
+            decision,
+            value,
+            weight,
+            bins,
+        }
+    }
+
+    pub fn validate(&self, problem: &Problem<T>) -> bool {
+        let mut value_sum = T::ZERO;
+        let mut weight_sum = T::ZERO;
         let mut valid = true;
 
         if self.decision.len() != problem.items.len() {
@@ -68,10 +1014,31 @@ impl Solution {
             valid = false;
         }
 
-        for (d, i) in self.decision.iter().zip(problem.items.iter()) {
-            if *d {
-                value_sum += i.value;
-                weight_sum += i.weight;
+        for (index, (d, i)) in self.decision.iter().zip(problem.items.iter()).enumerate() {
+            if *d > problem.copies(index) {
+                println!(
+                    "ERROR: Solution::validate, item {} taken {} times, only {} available",
+                    i.id,
+                    d,
+                    problem.copies(index)
+                );
+                valid = false;
+            }
+
+            let value = i.value.checked_mul(*d);
+            let weight = i.weight.checked_mul(*d);
+            match (
+                value.and_then(|v| value_sum.checked_add(v)),
+                weight.and_then(|w| weight_sum.checked_add(w)),
+            ) {
+                (Some(v), Some(w)) => {
+                    value_sum = v;
+                    weight_sum = w;
+                }
+                _ => {
+                    println!("ERROR: Solution::validate, sums overflow!");
+                    return false;
+                }
             }
         }
 
@@ -83,7 +1050,535 @@ impl Solution {
             println!("ERROR: Solution::validate, weight does not match!");
             valid = false;
         }
+        if problem.covering {
+            if weight_sum < problem.capacity {
+                println!("ERROR: Solution::validate, weight is under capacity!");
+                valid = false;
+            }
+        } else if problem.bins.is_empty() {
+            if weight_sum > problem.capacity {
+                println!("ERROR: Solution::validate, weight is over capacity!");
+                valid = false;
+            } else if problem.uncertainty.is_some()
+                && problem
+                    .robust_weight(&self.decision)
+                    .is_none_or(|weight| weight > problem.capacity)
+            {
+                println!("ERROR: Solution::validate, weight is over capacity when items deviate!");
+                valid = false;
+            }
+        } else if !self.validate_bins(problem) {
+            valid = false;
+        }
+
+        // The first dimension is the capacity checked above
+        for (number, dimension) in problem.dimensions.iter().enumerate() {
+            let weight = self
+                .decision
+                .iter()
+                .zip(&dimension.weights)
+                .try_fold(T::ZERO, |sum, (&d, &w)| {
+                    w.checked_mul(d).and_then(|w| sum.checked_add(w))
+                });
+            match weight {
+                Some(weight) if weight <= dimension.capacity => {}
+                Some(_) => {
+                    println!(
+                        "ERROR: Solution::validate, weight in dimension {} is over capacity!",
+                        number + 2
+                    );
+                    valid = false;
+                }
+                None => {
+                    println!("ERROR: Solution::validate, sums overflow!");
+                    return false;
+                }
+            }
+        }
+
+        if let Some(cardinality) = problem.cardinality {
+            let count = self.decision.iter().try_fold(0usize, |sum, d| {
+                d.to_usize().and_then(|d| sum.checked_add(d))
+            });
+            if !count.is_some_and(|count| cardinality.contains(count)) {
+                println!(
+                    "ERROR: Solution::validate, {:?} items taken, between {} and {} allowed",
+                    count, cardinality.min, cardinality.max
+                );
+                valid = false;
+            }
+        }
+
+        for (index, (&d, item)) in self.decision.iter().zip(&problem.items).enumerate() {
+            let broken = match problem.fixing(index) {
+                Fixing::Free => false,
+                Fixing::In => d == T::ZERO,
+                Fixing::Out => d != T::ZERO,
+            };
+            if broken {
+                println!(
+                    "ERROR: Solution::validate, item {} taken {} times but fixed {:?}",
+                    item.id,
+                    d,
+                    problem.fixing(index)
+                );
+                valid = false;
+            }
+        }
+
+        for &(first, second) in &problem.conflicts {
+            let taken = |index: usize| self.decision.get(index).is_some_and(|&d| d != T::ZERO);
+            if taken(first) && taken(second) {
+                println!(
+                    "ERROR: Solution::validate, items {} and {} conflict but are both taken",
+                    problem.items[first].id, problem.items[second].id
+                );
+                valid = false;
+            }
+        }
+
+        for &(before, after) in &problem.precedences {
+            let taken = |index: usize| self.decision.get(index).is_some_and(|&d| d != T::ZERO);
+            if taken(after) && !taken(before) {
+                println!(
+                    "ERROR: Solution::validate, item {} taken without item {} before it",
+                    problem.items[after].id, problem.items[before].id
+                );
+                valid = false;
+            }
+        }
+        if let Err(e) = problem.precedence_order() {
+            println!("ERROR: Solution::validate, {}", e);
+            valid = false;
+        }
+
+        // Exactly one item of each class
+        let mut class_counts = std::collections::BTreeMap::new();
+        for (d, i) in self.decision.iter().zip(problem.items.iter()) {
+            if let Some(class) = i.class {
+                *class_counts.entry(class).or_insert(T::ZERO) += *d;
+            }
+        }
+        for (class, count) in class_counts {
+            if count != T::ONE {
+                println!(
+                    "ERROR: Solution::validate, {} items taken from class {}",
+                    count, class
+                );
+                valid = false;
+            }
+        }
 
         valid
     }
+
+    /// Every taken item is in a bin, and every bin is within its capacity
+    fn validate_bins(&self, problem: &Problem<T>) -> bool {
+        let bins = match &self.bins {
+            Some(bins) if bins.len() == self.decision.len() => bins,
+            _ => {
+                println!("ERROR: Solution::validate, bin for each item missing");
+                return false;
+            }
+        };
+
+        let capacities = problem.bin_capacities();
+        let mut loads = vec![T::ZERO; capacities.len()];
+        let mut valid = true;
+        for ((&d, bin), item) in self.decision.iter().zip(bins).zip(&problem.items) {
+            match bin {
+                Some(bin) if d != T::ZERO && *bin < loads.len() => {
+                    // The weight sums above already fit
+                    loads[*bin] += item.weight * d;
+                }
+                None if d == T::ZERO => {}
+                _ => {
+                    println!(
+                        "ERROR: Solution::validate, item {} taken {} times but in bin {:?}",
+                        item.id, d, bin
+                    );
+                    valid = false;
+                }
+            }
+        }
+        for (bin, (load, capacity)) in loads.iter().zip(&capacities).enumerate() {
+            if load > capacity {
+                println!("ERROR: Solution::validate, bin {} is over capacity!", bin);
+                valid = false;
+            }
+        }
+        valid
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    fn read_error(input: &str) -> ParseError {
+        Problem::<u64>::read(input.as_bytes()).unwrap_err()
+    }
+
+    #[test]
+    fn read_valid() {
+        let problem = Problem::<u64>::read("2\n0 10 5\n\n1 20 7\n10\n\n".as_bytes()).unwrap();
+        assert_eq!(problem.items.len(), 2);
+        assert_eq!(problem.items[1].value, 20);
+        assert_eq!(problem.capacity, 10);
+    }
+
+    #[test]
+    fn read_missing_token() {
+        let e = read_error("2\n0 10 5\n1 20\n10\n");
+        assert_eq!((e.line, e.column), (3, 5));
+        assert!(matches!(
+            e.cause,
+            ParseErrorCause::MissingToken("item weight")
+        ));
+
+        let e = read_error("2\n0 10 5\n1 20 7\n");
+        assert_eq!(e.line, 4);
+        assert!(matches!(e.cause, ParseErrorCause::MissingToken("capacity")));
+    }
+
+    #[test]
+    fn read_non_numeric() {
+        let e = read_error("2\n0 10 5\n1  x2 7\n10\n");
+        assert_eq!((e.line, e.column), (3, 4));
+        assert!(matches!(e.cause, ParseErrorCause::NonNumeric { .. }));
+    }
+
+    #[test]
+    fn read_too_large() {
+        let e = Problem::<u32>::read("1\n0 10 5000000000\n10\n".as_bytes()).unwrap_err();
+        assert_eq!((e.line, e.column), (2, 6));
+        assert!(matches!(e.cause, ParseErrorCause::TooLarge { .. }));
+    }
+
+    #[test]
+    fn read_out_of_range() {
+        // Ids and counts are usize whatever the integer type, so widening would not help
+        let e = Problem::<u32>::read("1\n99999999999999999999 10 5\n10\n".as_bytes()).unwrap_err();
+        assert_eq!((e.line, e.column), (2, 1));
+        assert!(matches!(e.cause, ParseErrorCause::OutOfRange { .. }));
+
+        let e = Problem::<u128>::read("1\n0 10 5\n10\ngamma 99999999999999999999\n".as_bytes())
+            .unwrap_err();
+        assert!(matches!(e.cause, ParseErrorCause::OutOfRange { .. }));
+    }
+
+    #[test]
+    fn overflowing_sums() {
+        let items = vec![Item::new(0, u32::MAX - 5, 1), Item::new(1, 10, 1)];
+        let problem = Problem::new(items, 2u32);
+        assert!(matches!(
+            problem.check_overflow(),
+            Err(SolveError::Overflow(_))
+        ));
+
+        let items = vec![Item::new(0, 5, 1), Item::new(1, 10, 1)];
+        let problem = Problem::new(items, u32::MAX - 1);
+        assert!(problem.check_overflow().is_err());
+
+        let items = vec![Item::new(0, 5, 1), Item::new(1, 10, 1)];
+        let problem = Problem::new(items, u32::MAX - 2);
+        assert!(problem.check_overflow().is_ok());
+    }
+
+    #[test]
+    fn read_copies() {
+        let input = "2\n4 10 5\n7 20 7\n10\ncopies 7 3\n";
+        let problem = Problem::<u64>::read(input.as_bytes()).unwrap();
+        assert_eq!(problem.copies, Some(vec![1, 3]));
+        assert_eq!(problem.extensions(), vec![Extension::Copies]);
+
+        let e = read_error("2\n4 10 5\n7 20 7\n10\ncopies 5 3\n");
+        assert_eq!((e.line, e.column), (5, 8));
+        assert!(matches!(e.cause, ParseErrorCause::UnknownItem(5)));
+
+        let e = read_error("2\n4 10 5\n7 20 7\n10\ncopy 4 3\n");
+        assert!(matches!(e.cause, ParseErrorCause::UnknownKeyword(_)));
+    }
+
+    #[test]
+    fn read_classes() {
+        let input = "3\n4 10 5\n7 20 7\n9 15 6\n10\nclass 4 1\nclass 9 1\n";
+        let problem = Problem::<u64>::read(input.as_bytes()).unwrap();
+        let classes: Vec<Option<usize>> = problem.items.iter().map(|i| i.class).collect();
+        assert_eq!(classes, vec![Some(1), None, Some(1)]);
+        assert_eq!(problem.extensions(), vec![Extension::Classes]);
+
+        // Both items of class 1 taken
+        let solution = Solution::from_bools(&[true, false, true], 25, 11);
+        let problem = Problem::<u64>::read(input.replace("\n10\n", "\n20\n").as_bytes()).unwrap();
+        assert!(!solution.validate(&problem));
+    }
+
+    #[test]
+    fn read_dimensions() {
+        let input = "2\n4 10 5\n7 20 7\n10\ndimension 8\ndimension 3\nweights 7 6 2\n";
+        let problem = Problem::<u64>::read(input.as_bytes()).unwrap();
+        assert_eq!(problem.dimensions.len(), 2);
+        assert_eq!(problem.dimensions[0].weights, vec![0, 6]);
+        assert_eq!(problem.dimensions[1].capacity, 3);
+        assert_eq!(problem.extensions(), vec![Extension::Dimensions]);
+
+        // Fits the first dimension, but not the second
+        let mut problem = problem;
+        problem.dimensions[0].weights[0] = 3;
+        let solution = Solution::from_bools(&[true, false], 10, 5);
+        assert!(solution.validate(&problem));
+        let solution = Solution::from_bools(&[true, true], 30, 12);
+        problem.capacity = 12;
+        assert!(!solution.validate(&problem));
+
+        let e = read_error("2\n4 10 5\n7 20 7\n10\ndimension 8\nweights 7 6 2\n");
+        assert!(matches!(e.cause, ParseErrorCause::TrailingGarbage(_)));
+    }
+
+    #[test]
+    fn read_bins() {
+        let input = "3\n4 10 5\n7 20 7\n9 15 6\n10\nbins 6 8\n";
+        let problem = Problem::<u64>::read(input.as_bytes()).unwrap();
+        assert_eq!(problem.bin_capacities(), vec![10, 6, 8]);
+        assert_eq!(problem.extensions(), vec![Extension::Bins]);
+
+        let mut solution = Solution::from_bools(&[true, true, true], 45, 18);
+        solution.bins = Some(vec![Some(0), Some(2), Some(1)]);
+        assert!(solution.validate(&problem));
+        solution.bins = Some(vec![Some(1), Some(1), Some(0)]);
+        assert!(!solution.validate(&problem));
+        solution.bins = None;
+        assert!(!solution.validate(&problem));
+    }
+
+    #[test]
+    fn read_covering() {
+        let input = "3\n4 10 5\n7 20 7\n9 15 6\n12\ncovering\n";
+        let problem = Problem::<u64>::read(input.as_bytes()).unwrap();
+        assert!(problem.covering);
+        assert_eq!(problem.extensions(), vec![Extension::Covering]);
+
+        let solution = Solution::from_bools(&[true, false, false], 10, 5);
+        assert!(!solution.validate(&problem));
+        let solution = Solution::from_bools(&[true, true, false], 30, 12);
+        assert!(solution.validate(&problem));
+
+        // Leave out at most 18 - 12 = 6 weight
+        let packing = problem.complement().unwrap();
+        assert_eq!(packing.capacity, 6);
+        assert!(!packing.covering);
+        let cover = Solution::from_bools(&[false, false, true], 15, 6).complement(&problem);
+        assert_eq!(cover.decision, vec![1, 1, 0]);
+        assert!(cover.validate(&problem));
+
+        let problem = Problem::<u64>::read(input.replace("12\n", "19\n").as_bytes()).unwrap();
+        assert!(matches!(
+            problem.complement(),
+            Err(SolveError::Infeasible(_))
+        ));
+    }
+
+    #[test]
+    fn read_cardinality() {
+        let input = "3\n4 10 5\n7 20 7\n9 15 6\n20\ncardinality 2 2\n";
+        let problem = Problem::<u64>::read(input.as_bytes()).unwrap();
+        assert_eq!(problem.cardinality, Some(CountLimits { min: 2, max: 2 }));
+        assert_eq!(problem.extensions(), vec![Extension::Cardinality]);
+
+        let solution = Solution::from_bools(&[true, true, false], 30, 12);
+        assert!(solution.validate(&problem));
+        let solution = Solution::from_bools(&[false, true, false], 20, 7);
+        assert!(!solution.validate(&problem));
+
+        let e = read_error("3\n4 10 5\n7 20 7\n9 15 6\n20\ncardinality 2\n");
+        assert!(matches!(
+            e.cause,
+            ParseErrorCause::MissingToken("most item count")
+        ));
+    }
+
+    #[test]
+    fn read_conflicts() {
+        let input = "3\n4 10 5\n7 20 7\n9 15 6\n20\nconflict 4 9\n";
+        let mut problem = Problem::<u64>::read(input.as_bytes()).unwrap();
+        assert_eq!(problem.conflicts, vec![(0, 2)]);
+        assert_eq!(problem.extensions(), vec![Extension::Conflicts]);
+
+        let solution = Solution::from_bools(&[true, false, true], 25, 11);
+        assert!(!solution.validate(&problem));
+        let solution = Solution::from_bools(&[true, true, false], 30, 12);
+        assert!(solution.validate(&problem));
+
+        // Fixing item 4 in leaves item 9 out
+        problem.fix(0, Fixing::In);
+        let free = problem.without_fixings().unwrap();
+        assert_eq!(free.items.len(), 1);
+        assert!(free.conflicts.is_empty());
+        problem.fix(2, Fixing::In);
+        assert!(matches!(
+            problem.without_fixings(),
+            Err(SolveError::Infeasible(_))
+        ));
+    }
+
+    #[test]
+    fn read_precedences() {
+        let input = "3\n4 10 5\n7 20 7\n9 15 6\n20\nprecedence 4 7\nprecedence 7 9\n";
+        let mut problem = Problem::<u64>::read(input.as_bytes()).unwrap();
+        assert_eq!(problem.precedences, vec![(0, 1), (1, 2)]);
+        assert_eq!(problem.extensions(), vec![Extension::Precedences]);
+        assert_eq!(problem.precedence_order().unwrap(), vec![0, 1, 2]);
+
+        let solution = Solution::from_bools(&[true, false, true], 25, 11);
+        assert!(!solution.validate(&problem));
+        let solution = Solution::from_bools(&[true, true, false], 30, 12);
+        assert!(solution.validate(&problem));
+
+        // Item 7 in takes item 4 in, item 7 out leaves item 9 out
+        let mut closed = problem.clone();
+        closed.fix(1, Fixing::In);
+        closed.close_fixings().unwrap();
+        assert_eq!(
+            closed.fixings,
+            Some(vec![Fixing::In, Fixing::In, Fixing::Free])
+        );
+        closed.fix(1, Fixing::Out);
+        closed.fix(0, Fixing::Free);
+        closed.close_fixings().unwrap();
+        assert_eq!(
+            closed.fixings,
+            Some(vec![Fixing::Free, Fixing::Out, Fixing::Out])
+        );
+
+        problem.precedences.push((2, 0));
+        assert!(matches!(
+            problem.precedence_order(),
+            Err(SolveError::InvalidProblem(_))
+        ));
+        assert!(!solution.validate(&problem));
+    }
+
+    #[test]
+    fn read_second_values() {
+        let input = "3\n4 10 5\n7 20 7\n9 15 6\n20\nsecond_value 9 12\n";
+        let problem = Problem::<u64>::read(input.as_bytes()).unwrap();
+        assert_eq!(problem.second_values, Some(vec![0, 0, 12]));
+        assert_eq!(problem.second_value(2), 12);
+        assert_eq!(problem.extensions(), vec![Extension::SecondValues]);
+
+        let e = Problem::<u32>::read("1\n0 10 5\n10\nsecond_value 0 x\n".as_bytes()).unwrap_err();
+        assert!(matches!(e.cause, ParseErrorCause::NonNumeric { .. }));
+    }
+
+    #[test]
+    fn read_uncertainty() {
+        let input = "3\n4 10 5\n7 20 7\n9 15 6\n20\ndeviation 4 3\ndeviation 9 2\ngamma 1\n";
+        let problem = Problem::<u64>::read(input.as_bytes()).unwrap();
+        let uncertainty = problem.uncertainty.as_ref().unwrap();
+        assert_eq!(uncertainty.deviations, vec![3, 0, 2]);
+        assert_eq!(uncertainty.gamma, 1);
+        assert_eq!(problem.extensions(), vec![Extension::Uncertainty]);
+
+        // Only the largest deviation of the items taken counts
+        assert_eq!(problem.robust_weight(&[1, 0, 1]), Some(14));
+        assert_eq!(problem.robust_weight(&[0, 1, 1]), Some(15));
+
+        // Gamma defaults to every item
+        let input = "2\n0 10 5\n1 20 7\n15\ndeviation 0 2\ndeviation 1 2\n";
+        let problem = Problem::<u64>::read(input.as_bytes()).unwrap();
+        assert_eq!(problem.uncertainty.as_ref().unwrap().gamma, 2);
+        assert!(!Solution::from_bools(&[true, true], 30, 12).validate(&problem));
+    }
+
+    #[test]
+    fn read_fixings() {
+        let input = "3\n4 10 5\n7 20 7\n9 15 6\n20\nfix 7 in\nfix 9 out\n";
+        let problem = Problem::<u64>::read(input.as_bytes()).unwrap();
+        assert_eq!(
+            problem.fixings,
+            Some(vec![Fixing::Free, Fixing::In, Fixing::Out])
+        );
+        assert!(problem.extensions().is_empty());
+
+        let solution = Solution::from_bools(&[true, true, false], 30, 12);
+        assert!(solution.validate(&problem));
+        let solution = Solution::from_bools(&[true, false, true], 25, 11);
+        assert!(!solution.validate(&problem));
+
+        let e = read_error("3\n4 10 5\n7 20 7\n9 15 6\n20\nfix 7 maybe\n");
+        assert!(matches!(e.cause, ParseErrorCause::UnknownKeyword(_)));
+    }
+
+    #[test]
+    fn fixings_reduce_the_problem() {
+        let items = vec![
+            Item::new(0, 10u64, 5),
+            Item::new(1, 20, 7),
+            Item::new(2, 15, 6),
+            Item::new(3, 5, 2),
+        ];
+        let mut problem = Problem::new(items, 20);
+        problem.items[1].class = Some(0);
+        problem.items[2].class = Some(0);
+        problem.copies = Some(vec![3, 1, 1, 2]);
+        problem.cardinality = Some(CountLimits { min: 3, max: 4 });
+        problem.fix(0, Fixing::In);
+        problem.fix(2, Fixing::In);
+        problem.fix(3, Fixing::Out);
+
+        // Class 0 is decided by item 2, and item 0 has two copies left to take
+        let free = problem.without_fixings().unwrap();
+        assert_eq!(free.items.len(), 1);
+        assert_eq!(free.items[0].id, 0);
+        assert_eq!(free.copies, Some(vec![2]));
+        assert_eq!(free.capacity, 9);
+        assert_eq!(free.cardinality, Some(CountLimits { min: 1, max: 2 }));
+
+        let solution = Solution {
+            decision: vec![1],
+            value: 10,
+            weight: 5,
+            bins: None,
+        }
+        .with_fixings(&problem);
+        assert_eq!(solution.decision, vec![2, 0, 1, 0]);
+        assert!(solution.validate(&problem));
+
+        problem.fix(1, Fixing::In);
+        assert!(matches!(
+            problem.without_fixings(),
+            Err(SolveError::Infeasible(_))
+        ));
+        problem.fix(1, Fixing::Out);
+        problem.fix(2, Fixing::Out);
+        assert!(matches!(
+            problem.without_fixings(),
+            Err(SolveError::Infeasible(_))
+        ));
+    }
+
+    #[test]
+    fn read_count_mismatch() {
+        let e = read_error("3\n0 10 5\n1 20 7\n10\n");
+        assert_eq!(e.line, 4);
+        assert!(matches!(
+            e.cause,
+            ParseErrorCause::CountMismatch {
+                expected: 3,
+                found: 2
+            }
+        ));
+    }
+
+    #[test]
+    fn read_trailing_garbage() {
+        let e = read_error("2\n0 10 5 3\n1 20 7\n10\n");
+        assert_eq!((e.line, e.column), (2, 8));
+        assert!(matches!(e.cause, ParseErrorCause::TrailingGarbage(_)));
+
+        let e = read_error("2\n0 10 5\n1 20 7\n10\n11\n");
+        assert_eq!(e.line, 5);
+        assert!(matches!(e.cause, ParseErrorCause::TrailingGarbage(_)));
+    }
 }
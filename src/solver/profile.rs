@@ -0,0 +1,299 @@
+// Capacity profile, the optimal value of a problem for every capacity in a range at once
+//
+// The states of a knapsack dynamic program that no other state dominates, weighing no more
+// and worth at least as much, already hold the answer for every smaller capacity: the best
+// solution for a capacity is the heaviest state that fits. States are built as minknap and
+// Nemhauser-Ullmann build them, adding items one at a time to a list ordered by weight and
+// merging the states that leave the item out with those that take it. Only states that fit
+// the highest capacity asked for are kept, so one pass costs no more than solving at that
+// capacity by dynamic programming.
+//
+// With the states ordered by weight their values rise too, so the optimal value is a step
+// function of the capacity, with one step at the weight of each state.
+
+use crate::solver::dynamic::MAX_BYTES;
+use crate::solver::integer::*;
+use crate::solver::problem::*;
+use crate::solver::registry::*;
+use std::mem::size_of;
+
+/// The optimal solution from one capacity on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileStep<T> {
+    pub capacity: T,
+    pub value: T,
+    pub weight: T,
+    /// Items taken, when decisions are asked for
+    pub decision: Option<Vec<T>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct State<T> {
+    value: T,
+    weight: T,
+    /// Index in the trail of the last item taken, None if no item is taken
+    /// or no decisions are kept
+    trail: Option<usize>,
+}
+
+/// An item taken, linked to the item taken before it
+struct TrailStep {
+    item: usize,
+    previous: Option<usize>,
+}
+
+struct Instance<'a, T> {
+    problem: &'a Problem<T>,
+    /// Highest capacity asked for
+    capacity: T,
+    keep_decisions: bool,
+    trail: Vec<TrailStep>,
+}
+
+impl<'a, T: Integer> Instance<'a, T> {
+    fn take(&mut self, state: &State<T>, index: usize) -> State<T> {
+        let item = &self.problem.items[index];
+        let trail = if self.keep_decisions {
+            self.trail.push(TrailStep {
+                item: index,
+                previous: state.trail,
+            });
+            Some(self.trail.len() - 1)
+        } else {
+            None
+        };
+        State {
+            value: state.value + item.value,
+            weight: state.weight + item.weight,
+            trail,
+        }
+    }
+
+    /// Merge the states leaving the item at `index` out with those taking it,
+    /// lightest first, and keep each state worth more than every lighter state
+    fn add_item(&mut self, index: usize, states: &[State<T>], next_states: &mut Vec<State<T>>) {
+        let item = self.problem.items[index];
+        let state_count = states.len();
+        next_states.clear();
+
+        let mut keep_index = 0;
+        let mut change_index = 0;
+        while keep_index != state_count || change_index != state_count {
+            // States are ordered by weight, so once a changed state is too heavy all are
+            if change_index != state_count
+                && states[change_index].weight + item.weight > self.capacity
+            {
+                change_index = state_count;
+                continue;
+            }
+            let take_change = change_index != state_count
+                && (keep_index == state_count || {
+                    let changed = &states[change_index];
+                    let kept = &states[keep_index];
+                    let weight = changed.weight + item.weight;
+                    weight < kept.weight
+                        || (weight == kept.weight && changed.value + item.value > kept.value)
+                });
+
+            let (value, state) = if take_change {
+                change_index += 1;
+                let parent = &states[change_index - 1];
+                (parent.value + item.value, None)
+            } else {
+                keep_index += 1;
+                (states[keep_index - 1].value, Some(states[keep_index - 1]))
+            };
+            if next_states.last().is_some_and(|last| last.value >= value) {
+                continue;
+            }
+            // Only states that are kept get a step in the trail
+            let state = match state {
+                Some(state) => state,
+                None => self.take(&states[change_index - 1], index),
+            };
+            next_states.push(state);
+        }
+    }
+
+    /// Every state no other state dominates, ordered by weight and value
+    fn states(&mut self) -> Result<Vec<State<T>>, SolveError> {
+        let mut root = State {
+            value: T::ZERO,
+            weight: T::ZERO,
+            trail: None,
+        };
+        for index in 0..self.problem.items.len() {
+            if self.problem.fixing(index) == Fixing::In {
+                root = self.take(&root, index);
+            }
+        }
+        if root.weight > self.capacity {
+            return Err(SolveError::Infeasible(format!(
+                "the items fixed in weigh {}, over the highest capacity of {}",
+                root.weight, self.capacity
+            )));
+        }
+
+        let mut states = vec![root];
+        let mut next_states = Vec::new();
+        for index in 0..self.problem.items.len() {
+            if self.problem.fixing(index) != Fixing::Free {
+                continue;
+            }
+            self.add_item(index, &states, &mut next_states);
+            std::mem::swap(&mut states, &mut next_states);
+
+            let bytes_used = (states.len() + next_states.len()) * size_of::<State<T>>()
+                + self.trail.len() * size_of::<TrailStep>();
+            if bytes_used > MAX_BYTES {
+                return Err(SolveError::TooLarge {
+                    size: bytes_used,
+                    limit: MAX_BYTES,
+                });
+            }
+        }
+        Ok(states)
+    }
+
+    fn step(&self, capacity: T, state: &State<T>) -> ProfileStep<T> {
+        let decision = self.keep_decisions.then(|| {
+            let mut decision = vec![T::ZERO; self.problem.items.len()];
+            let mut step = state.trail;
+            while let Some(index) = step {
+                decision[self.trail[index].item] = T::ONE;
+                step = self.trail[index].previous;
+            }
+            decision
+        });
+        ProfileStep {
+            capacity,
+            value: state.value,
+            weight: state.weight,
+            decision,
+        }
+    }
+}
+
+fn instance<T: Integer>(
+    problem: &Problem<T>,
+    capacity: T,
+    keep_decisions: bool,
+) -> Result<Instance<'_, T>, SolveError> {
+    problem.check_supported("capacity profile", &[])?;
+    // The sums only need to fit with the highest capacity asked for
+    Problem::new(problem.items.clone(), capacity).check_overflow()?;
+    Ok(Instance {
+        problem,
+        capacity,
+        keep_decisions,
+        trail: Vec::new(),
+    })
+}
+
+/// The optimal solution for every capacity from `low` to `high`, as a step function.
+/// The first step is at `low`, or at the weight of the items fixed in if that is more,
+/// and each other step is at the capacity where the optimal value rises.
+/// The problem's own capacity is ignored
+pub fn capacity_profile<T: Integer>(
+    problem: &Problem<T>,
+    low: T,
+    high: T,
+    keep_decisions: bool,
+) -> Result<Vec<ProfileStep<T>>, SolveError> {
+    if low > high {
+        return Err(SolveError::InvalidProblem(format!(
+            "the capacity range from {} to {} is empty",
+            low, high
+        )));
+    }
+    let mut instance = instance(problem, high, keep_decisions)?;
+    let states = instance.states()?;
+    // The heaviest state that fits `low`, or the lightest state if none do
+    let first = states
+        .partition_point(|state| state.weight <= low)
+        .saturating_sub(1);
+    Ok(states[first..]
+        .iter()
+        .map(|state| instance.step(state.weight.max(low), state))
+        .collect())
+}
+
+/// The optimal solution for each of the capacities, in the order given.
+/// Capacities too small for the items fixed in are left out.
+/// The problem's own capacity is ignored
+pub fn capacity_grid<T: Integer>(
+    problem: &Problem<T>,
+    capacities: &[T],
+    keep_decisions: bool,
+) -> Result<Vec<ProfileStep<T>>, SolveError> {
+    let high = match capacities.iter().max() {
+        Some(&high) => high,
+        None => return Ok(Vec::new()),
+    };
+    let mut instance = instance(problem, high, keep_decisions)?;
+    let states = instance.states()?;
+    Ok(capacities
+        .iter()
+        .filter_map(|&capacity| {
+            let fitting = states.partition_point(|state| state.weight <= capacity);
+            fitting
+                .checked_sub(1)
+                .map(|last| instance.step(capacity, &states[last]))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::solver::dynamic;
+    use crate::solver::test_util::Lcg;
+
+    fn example_problem(item_count: usize, seed: u64) -> Problem<u64> {
+        let items = Lcg::new(seed).items(item_count, 0..40, 1..26);
+        Problem::new(items, 0)
+    }
+
+    #[test]
+    fn matches_dynamic_at_every_capacity() {
+        for seed in 0..5 {
+            let mut problem = example_problem(15, seed);
+            let steps = capacity_profile(&problem, 20, 120, true).unwrap();
+            assert_eq!(steps[0].capacity, 20);
+            assert!(steps.windows(2).all(|pair| pair[0].value < pair[1].value));
+
+            for capacity in 20..=120 {
+                problem.capacity = capacity;
+                let optimum = dynamic::solve(&problem).unwrap().value;
+                let last = steps.partition_point(|step| step.capacity <= capacity) - 1;
+                let step = &steps[last];
+                assert_eq!(step.value, optimum, "seed {} capacity {}", seed, capacity);
+                let solution = Solution {
+                    decision: step.decision.clone().unwrap(),
+                    value: step.value,
+                    weight: step.weight,
+                    bins: None,
+                };
+                assert!(solution.validate(&problem));
+            }
+        }
+    }
+
+    #[test]
+    fn grid_with_fixings() {
+        let mut problem = example_problem(12, 7);
+        problem.fix(0, Fixing::In);
+        problem.fix(1, Fixing::Out);
+        let fixed_weight = problem.items[0].weight;
+        let capacities = vec![90, fixed_weight - 1, 30, fixed_weight];
+        let steps = capacity_grid(&problem, &capacities, false).unwrap();
+        let listed: Vec<u64> = steps.iter().map(|step| step.capacity).collect();
+        assert_eq!(listed, vec![90, 30, fixed_weight]);
+
+        for step in &steps {
+            assert_eq!(step.decision, None);
+            problem.capacity = step.capacity;
+            assert_eq!(step.value, dynamic::solve(&problem).unwrap().value);
+        }
+    }
+}
@@ -0,0 +1,299 @@
+use crate::solver::integer::*;
+use crate::solver::problem::*;
+use crate::solver::{
+    biobjective, bounded, cardinality, conflict, dynamic, expknap, fractional, greedy, mcknap,
+    minknap, multidim, multiple, precedence, robust, subset_sum, unbounded,
+};
+
+/// Options shared by every solver implementation
+#[derive(Debug, Clone, Default)]
+pub struct SolveConfig {
+    /// Print progress updates while solving
+    pub verbose: bool,
+}
+
+/// Everything a solver reports back about a run
+#[derive(Debug)]
+pub struct SolveReport<T = u64> {
+    pub solution: Solution<T>,
+    /// Number of states explored, for solvers that keep track
+    pub states_explored: Option<usize>,
+    /// Estimate of the memory used in bytes, for solvers that keep track
+    pub bytes_used: Option<usize>,
+}
+
+impl<T: Integer> SolveReport<T> {
+    pub fn new(solution: Solution<T>) -> SolveReport<T> {
+        SolveReport {
+            solution,
+            states_explored: None,
+            bytes_used: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SolveError {
+    /// The problem needs more memory than the solver is willing to use
+    TooLarge { size: usize, limit: usize },
+    /// No solver is registered under the requested name
+    UnknownSolver(String),
+    /// A sum the solver needs does not fit in the integer type
+    Overflow(&'static str),
+    /// The problem has no meaningful solution, or breaks the solver's assumptions
+    InvalidProblem(String),
+    /// No choice of items satisfies the problem's constraints
+    Infeasible(String),
+    /// The problem uses an extension the solver can not handle
+    Unsupported {
+        solver: &'static str,
+        extension: Extension,
+    },
+}
+
+impl std::fmt::Display for SolveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SolveError::TooLarge { size, limit } => {
+                write!(f, "Problem exceeds size limit: {} > {}", size, limit)
+            }
+            SolveError::UnknownSolver(name) => write!(f, "No solver named {}", name),
+            SolveError::Overflow(sum) => {
+                write!(f, "The {} overflows, try a wider integer type", sum)
+            }
+            SolveError::InvalidProblem(reason) => write!(f, "Invalid problem, {}", reason),
+            SolveError::Infeasible(reason) => write!(f, "Problem is infeasible, {}", reason),
+            SolveError::Unsupported { solver, extension } => {
+                write!(f, "The {} solver does not support {}", solver, extension)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SolveError {}
+
+/// Common interface for all solver implementations
+pub trait KnapsackSolver<T: Integer> {
+    /// Name used to look the solver up in a `SolverRegistry`
+    fn name(&self) -> &'static str;
+
+    fn solve(
+        &self,
+        problem: &Problem<T>,
+        config: &SolveConfig,
+    ) -> Result<SolveReport<T>, SolveError>;
+}
+
+/// Solve a 0-1 covering problem with a packing solver,
+/// by packing the most valuable items to leave out of the cover
+pub fn solve_covering<T: Integer>(
+    solver: &dyn KnapsackSolver<T>,
+    problem: &Problem<T>,
+    config: &SolveConfig,
+) -> Result<SolveReport<T>, SolveError> {
+    problem.check_overflow()?;
+    let packing = problem.complement()?;
+    let mut report = solver.solve(&packing, config)?;
+    report.solution = report.solution.complement(problem);
+    Ok(report)
+}
+
+/// Solve a problem with fixed items with a solver that ignores fixings,
+/// by solving for the free items and adding the items fixed in back
+pub fn solve_fixed<T: Integer>(
+    solver: &dyn KnapsackSolver<T>,
+    problem: &Problem<T>,
+    config: &SolveConfig,
+) -> Result<SolveReport<T>, SolveError> {
+    problem.check_overflow()?;
+    let mut closed = problem.clone();
+    closed.close_fixings()?;
+    let free = closed.without_fixings()?;
+    let mut report = solver.solve(&free, config)?;
+    report.solution = report.solution.with_fixings(&closed);
+    Ok(report)
+}
+
+/// Collection of solvers that can be looked up by name
+pub struct SolverRegistry<T: Integer = u64> {
+    solvers: Vec<Box<dyn KnapsackSolver<T>>>,
+}
+
+impl<T: Integer> SolverRegistry<T> {
+    /// An empty registry, see `SolverRegistry::default` for one with the built-in solvers
+    pub fn new() -> SolverRegistry<T> {
+        SolverRegistry {
+            solvers: Vec::new(),
+        }
+    }
+
+    /// Add a solver, replacing any solver already registered under the same name
+    pub fn register(&mut self, solver: Box<dyn KnapsackSolver<T>>) {
+        self.solvers
+            .retain(|s| !s.name().eq_ignore_ascii_case(solver.name()));
+        self.solvers.push(solver);
+    }
+
+    /// Look up a solver by name, ignoring case
+    pub fn get(&self, name: &str) -> Result<&dyn KnapsackSolver<T>, SolveError> {
+        self.solvers
+            .iter()
+            .find(|s| s.name().eq_ignore_ascii_case(name))
+            .map(|s| s.as_ref())
+            .ok_or_else(|| SolveError::UnknownSolver(name.to_string()))
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.solvers.iter().map(|s| s.name()).collect()
+    }
+}
+
+impl<T: Integer> Default for SolverRegistry<T> {
+    fn default() -> SolverRegistry<T> {
+        let mut registry = SolverRegistry::new();
+        registry.register(Box::new(greedy::Greedy));
+        registry.register(Box::new(dynamic::Dynamic));
+        registry.register(Box::new(minknap::Minknap));
+        registry.register(Box::new(expknap::Expknap));
+        registry.register(Box::new(bounded::Bounded));
+        registry.register(Box::new(unbounded::Unbounded));
+        registry.register(Box::new(mcknap::Mcknap));
+        registry.register(Box::new(multidim::Multidim));
+        registry.register(Box::new(multiple::Multiple));
+        registry.register(Box::new(multiple::MultipleExact));
+        registry.register(Box::new(subset_sum::SubsetSum));
+        registry.register(Box::new(fractional::Fractional));
+        registry.register(Box::new(cardinality::Cardinality));
+        registry.register(Box::new(conflict::Conflict));
+        registry.register(Box::new(precedence::Precedence));
+        registry.register(Box::new(biobjective::Biobjective));
+        registry.register(Box::new(robust::Robust));
+        registry
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    fn example_problem() -> Problem<u32> {
+        let items = vec![
+            Item::new(0, 10, 5),
+            Item::new(1, 40, 4),
+            Item::new(2, 30, 6),
+            Item::new(3, 50, 3),
+        ];
+        Problem::new(items, 10)
+    }
+
+    #[test]
+    fn lookup_ignores_case() {
+        let registry = SolverRegistry::<u64>::default();
+        assert_eq!(registry.get("minknap").unwrap().name(), "Minknap");
+        assert_eq!(registry.get("DYNAMIC").unwrap().name(), "Dynamic");
+        assert!(registry.get("Simplex").is_err());
+    }
+
+    #[test]
+    fn register_replaces_same_name() {
+        let mut registry = SolverRegistry::<u64>::default();
+        let count = registry.names().len();
+        registry.register(Box::new(minknap::Minknap));
+        assert_eq!(registry.names().len(), count);
+    }
+
+    #[test]
+    fn exact_solvers_agree() {
+        let problem = example_problem();
+        let registry = SolverRegistry::default();
+        let config = SolveConfig::default();
+        for name in ["Dynamic", "Minknap", "Expknap"] {
+            let report = registry
+                .get(name)
+                .unwrap()
+                .solve(&problem, &config)
+                .unwrap();
+            assert!(report.solution.validate(&problem));
+            assert_eq!(report.solution.value, 90);
+        }
+    }
+
+    #[test]
+    fn covering_solvers_agree() {
+        let mut problem = example_problem();
+        problem.covering = true;
+        let registry = SolverRegistry::default();
+        let config = SolveConfig::default();
+        // Items 0 and 2 weigh 11 for a value of 40, only item 1 is cheaper to add
+        for (capacity, cost) in [(0, 0), (9, 40), (11, 40), (12, 80), (18, 130)] {
+            problem.capacity = capacity;
+            for name in ["Dynamic", "Minknap", "Expknap"] {
+                let report = registry
+                    .get(name)
+                    .unwrap()
+                    .solve(&problem, &config)
+                    .unwrap();
+                assert!(report.solution.validate(&problem));
+                assert_eq!(report.solution.value, cost, "{} {}", name, capacity);
+            }
+        }
+
+        problem.capacity = 19;
+        let report = registry.get("Minknap").unwrap().solve(&problem, &config);
+        assert!(matches!(report, Err(SolveError::Infeasible(_))));
+    }
+
+    #[test]
+    fn fixed_solvers_agree() {
+        let mut problem = example_problem();
+        let registry = SolverRegistry::default();
+        let config = SolveConfig::default();
+        problem.fix(2, Fixing::In);
+        // Item 2 leaves 4 capacity, for item 3 and then item 1 once 3 is fixed out
+        for (fixed_out, best) in [(None, 80), (Some(3), 70)] {
+            if let Some(index) = fixed_out {
+                problem.fix(index, Fixing::Out);
+            }
+            for name in [
+                "Dynamic",
+                "Minknap",
+                "Expknap",
+                "Bounded",
+                "Mcknap",
+                "Multidim",
+                "MultipleExact",
+                "Cardinality",
+            ] {
+                let report = registry
+                    .get(name)
+                    .unwrap()
+                    .solve(&problem, &config)
+                    .unwrap();
+                assert!(report.solution.validate(&problem), "{}", name);
+                assert_eq!(report.solution.value, best, "{}", name);
+            }
+        }
+
+        // Without item 3, covering 12 takes every other item
+        problem.fix(2, Fixing::Free);
+        problem.covering = true;
+        problem.capacity = 12;
+        for name in ["Dynamic", "Minknap", "Expknap"] {
+            let report = registry
+                .get(name)
+                .unwrap()
+                .solve(&problem, &config)
+                .unwrap();
+            assert!(report.solution.validate(&problem));
+            assert_eq!(report.solution.value, 80, "{}", name);
+        }
+
+        let mut problem = example_problem();
+        problem.fix(0, Fixing::In);
+        problem.fix(2, Fixing::In);
+        for name in ["Dynamic", "Minknap", "Expknap"] {
+            let report = registry.get(name).unwrap().solve(&problem, &config);
+            assert!(matches!(report, Err(SolveError::Infeasible(_))), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,184 @@
+// Robust knapsack problem with budgeted uncertainty, after Bertsimas and Sim, where each
+// item may weigh up to its deviation more than its nominal weight, but at most gamma items
+// deviate at once
+//
+// A solution must fit when the gamma items it takes with the largest deviations all
+// deviate. That worst case is a linear program over which items deviate, and its dual
+// turns the robust weight into a minimum over a threshold theta:
+//
+//     gamma * theta + sum of (weight + max(deviation - theta, 0)) over the items taken
+//
+// The minimum is reached with theta at 0 or at one of the deviations, so the robust
+// problem is the best of at most n + 1 nominal problems. For each theta, items weigh
+// their nominal weight plus whatever of their deviation passes theta, and the capacity
+// is reduced by gamma * theta. Each is solved by minknap, and any of their solutions is
+// robust, as its robust weight is at most the weight of its own nominal problem.
+
+use crate::solver::integer::*;
+use crate::solver::minknap;
+use crate::solver::problem::*;
+use crate::solver::registry::*;
+
+/// Solves a nominal knapsack for every distinct deviation, and keeps the best
+pub struct Robust;
+
+impl<T: Integer> KnapsackSolver<T> for Robust {
+    fn name(&self) -> &'static str {
+        "Robust"
+    }
+
+    fn solve(
+        &self,
+        problem: &Problem<T>,
+        config: &SolveConfig,
+    ) -> Result<SolveReport<T>, SolveError> {
+        problem.check_supported("Robust", &[Extension::Uncertainty])?;
+        problem.check_overflow()?;
+        let item_count = problem.items.len();
+        let (deviations, gamma) = match &problem.uncertainty {
+            Some(uncertainty) => (uncertainty.deviations.clone(), uncertainty.gamma),
+            None => (vec![T::ZERO; item_count], 0),
+        };
+        let gamma = T::from_usize(gamma).unwrap_or(T::MAX);
+
+        let mut thetas = deviations.clone();
+        thetas.push(T::ZERO);
+        thetas.sort_unstable();
+        thetas.dedup();
+
+        let mut best: Option<(Solution<T>, T)> = None;
+        let mut knapsacks_solved = 0;
+        for theta in thetas {
+            let capacity = match gamma
+                .checked_mul(theta)
+                .and_then(|reserved| problem.capacity.checked_sub(reserved))
+            {
+                Some(capacity) => capacity,
+                None => continue,
+            };
+            // Problem::check_overflow guarantees the weights with their deviations fit
+            let items = problem
+                .items
+                .iter()
+                .zip(&deviations)
+                .map(|(item, &deviation)| {
+                    let extra = if deviation > theta {
+                        deviation - theta
+                    } else {
+                        T::ZERO
+                    };
+                    Item::new(item.id, item.value, item.weight + extra)
+                })
+                .collect();
+            let mut nominal = Problem::new(items, capacity);
+            nominal.fixings.clone_from(&problem.fixings);
+
+            knapsacks_solved += 1;
+            let solution = match minknap::solve(&nominal) {
+                Ok(solution) => solution,
+                Err(SolveError::Infeasible(_)) => continue,
+                Err(error) => return Err(error),
+            };
+            if best
+                .as_ref()
+                .is_none_or(|(best, _)| solution.value > best.value)
+            {
+                best = Some((solution, theta));
+            }
+        }
+
+        let (mut solution, theta) = best.ok_or_else(|| {
+            SolveError::Infeasible(
+                "the items fixed in do not fit when their weights deviate".to_string(),
+            )
+        })?;
+        solution.weight = solution
+            .decision
+            .iter()
+            .zip(&problem.items)
+            .map(|(&d, item)| d * item.weight)
+            .sum();
+        if config.verbose {
+            println!(
+                "knapsacks solved: {}, best theta: {}, robust weight: {}",
+                knapsacks_solved,
+                theta,
+                // The robust weight is at most the weight of the nominal problem solved
+                problem.robust_weight(&solution.decision).unwrap()
+            );
+        }
+        Ok(SolveReport {
+            solution,
+            states_explored: None,
+            bytes_used: None,
+        })
+    }
+}
+
+pub fn solve<T: Integer>(problem: &Problem<T>) -> Result<Solution<T>, SolveError> {
+    let report = Robust.solve(problem, &SolveConfig::default())?;
+    Ok(report.solution)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::solver::test_util::{subsets, Lcg};
+
+    /// Pseudo random problem where each item may deviate by up to its nominal weight
+    fn example_problem(item_count: usize, gamma: usize, seed: u64) -> Problem<u64> {
+        let mut rng = Lcg::new(seed);
+        let items = rng.items(item_count, 1..51, 0..30);
+        let deviations = items
+            .iter()
+            .map(|item| rng.below(item.weight + 1))
+            .collect();
+        let mut problem = Problem::new(items, 6 * item_count as u64);
+        problem.uncertainty = Some(Uncertainty { deviations, gamma });
+        problem
+    }
+
+    fn brute_force(problem: &Problem<u64>) -> u64 {
+        subsets(problem)
+            .filter(|(taken, _, _)| {
+                let decision: Vec<u64> = taken.iter().map(|&taken| taken as u64).collect();
+                problem.robust_weight(&decision).unwrap() <= problem.capacity
+            })
+            .map(|(_, value, _)| value)
+            .max()
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        for seed in 0..20 {
+            let problem = example_problem(12, seed as usize % 6, seed);
+            let solution = solve(&problem).unwrap();
+            assert!(solution.validate(&problem));
+            assert_eq!(solution.value, brute_force(&problem), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn deviations_must_fit() {
+        let items = vec![
+            Item::new(0, 10u32, 4),
+            Item::new(1, 10, 4),
+            Item::new(2, 6, 4),
+        ];
+        let mut problem = Problem::new(items, 11);
+        problem.uncertainty = Some(Uncertainty {
+            deviations: vec![3, 3, 0],
+            gamma: 2,
+        });
+
+        // Items 0 and 1 fit nominally, but not once both of them deviate
+        let nominal = Solution::from_bools(&[true, true, false], 20, 8);
+        assert!(!nominal.validate(&problem));
+
+        let solution = solve(&problem).unwrap();
+        assert!(solution.validate(&problem));
+        assert_eq!(solution.value, 16);
+        assert_eq!(solution.weight, 8);
+    }
+}
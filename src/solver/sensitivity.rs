@@ -0,0 +1,274 @@
+// Sensitivity analysis of an optimal solution, how far each item's value and weight, and
+// the capacity, can change before another solution beats it
+//
+// Changing an item only changes the solutions that take it. The solution stays optimal
+// while the best solution on the other side of the item is worth no more than it:
+//
+// - An item taken may lose value until it is worth no more than the best solution leaving
+//   it out, and gain any amount. An item left out may gain value up to the same margin
+//   against the best solution taking it, and lose any amount.
+// - An item left out may gain any amount of weight, and an item taken may gain weight
+//   until the solution no longer fits. Either may lose weight until the room it frees lets
+//   the other items reach more than the optimal value less its own. That is the lightest
+//   set of the other items worth more than that, a covering knapsack over the items with
+//   their values and weights swapped.
+// - The capacity may fall to the weight of the solution, and rise until it fits the
+//   lightest set of items worth more than the solution, again a covering knapsack.
+//
+// Each bound is one minknap solve, so the analysis takes two for each item and one more.
+
+use crate::solver::integer::*;
+use crate::solver::minknap;
+use crate::solver::problem::*;
+use crate::solver::registry::*;
+
+/// A range of numbers, from `min` to `max` inclusive, or with no upper limit if `max` is None
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interval<T> {
+    pub min: T,
+    pub max: Option<T>,
+}
+
+impl<T: Integer> std::fmt::Display for Interval<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.max {
+            Some(max) => write!(f, "{}..{}", self.min, max),
+            None => write!(f, "{}..", self.min),
+        }
+    }
+}
+
+/// How far one item can change while the solution stays optimal, the other items
+/// and the capacity staying as they are
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ItemSensitivity<T> {
+    pub taken: bool,
+    pub value: Interval<T>,
+    pub weight: Interval<T>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sensitivity<T> {
+    /// Indexed like `Problem::items`
+    pub items: Vec<ItemSensitivity<T>>,
+    /// Capacities the solution stays optimal for
+    pub capacity: Interval<T>,
+}
+
+/// Value of the best solution with the item at `index` fixed, None if none fit
+fn fixed_optimum<T: Integer>(
+    problem: &Problem<T>,
+    index: usize,
+    fixing: Fixing,
+) -> Result<Option<T>, SolveError> {
+    let mut fixed = problem.clone();
+    fixed.fix(index, fixing);
+    match minknap::solve(&fixed) {
+        Ok(solution) => Ok(Some(solution.value)),
+        Err(SolveError::Infeasible(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Every solution takes or leaves out each item, so comparing against the best solution
+/// on the other side of any item is enough to tell whether the solution is optimal
+fn check_optimal<T: Integer>(optimum: T, other: T) -> Result<(), SolveError> {
+    if other > optimum {
+        return Err(SolveError::InvalidProblem(format!(
+            "the solution worth {} is not optimal, another is worth {}",
+            optimum, other
+        )));
+    }
+    Ok(())
+}
+
+/// Least weight of items worth at least `target`, leaving out the item at `excluded`.
+/// None if the items together are worth less
+fn lightest_worth<T: Integer>(
+    problem: &Problem<T>,
+    target: T,
+    excluded: Option<usize>,
+) -> Result<Option<T>, SolveError> {
+    let items = problem
+        .items
+        .iter()
+        .map(|item| Item::new(item.id, item.weight, item.value))
+        .collect();
+    let mut covering = Problem::new(items, target);
+    covering.covering = true;
+    if let Some(index) = excluded {
+        covering.fix(index, Fixing::Out);
+    }
+    match minknap::solve(&covering) {
+        Ok(solution) => Ok(Some(solution.value)),
+        Err(SolveError::Infeasible(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// How far each item, and the capacity, can change before `solution` is no longer optimal.
+/// The solution must be optimal for the problem, as `minknap::solve` returns it
+pub fn analyse<T: Integer>(
+    problem: &Problem<T>,
+    solution: &Solution<T>,
+) -> Result<Sensitivity<T>, SolveError> {
+    problem.check_supported("sensitivity analysis", &[])?;
+    if problem.fixings.is_some() {
+        return Err(SolveError::InvalidProblem(
+            "sensitivity analysis needs every item to be free".to_string(),
+        ));
+    }
+    problem.check_overflow()?;
+    let optimum = solution.value;
+    let capacity = problem.capacity;
+
+    let mut items = Vec::with_capacity(problem.items.len());
+    for (index, item) in problem.items.iter().enumerate() {
+        let taken = solution.decision[index] != T::ZERO;
+        let value = if taken {
+            // Leaving the item out is always feasible
+            let other = fixed_optimum(problem, index, Fixing::Out)?.unwrap_or(T::ZERO);
+            check_optimal(optimum, other)?;
+            Interval {
+                min: item.value.saturating_sub(optimum - other),
+                max: None,
+            }
+        } else {
+            let other = fixed_optimum(problem, index, Fixing::In)?;
+            check_optimal(optimum, other.unwrap_or(T::ZERO))?;
+            Interval {
+                min: T::ZERO,
+                max: other.map(|other| item.value + (optimum - other)),
+            }
+        };
+
+        // The solution stays optimal while the other items, in the room the item leaves,
+        // are worth no more than the optimum less the item's value
+        let min_weight = if item.value > optimum {
+            capacity.saturating_add(T::ONE)
+        } else {
+            match lightest_worth(problem, optimum - item.value + T::ONE, Some(index))? {
+                None => T::ZERO,
+                Some(weight) if weight == T::ZERO => capacity.saturating_add(T::ONE),
+                Some(weight) => capacity.saturating_sub(weight - T::ONE),
+            }
+        };
+        let max_weight = if taken {
+            Some(item.weight + (capacity - solution.weight))
+        } else {
+            None
+        };
+        items.push(ItemSensitivity {
+            taken,
+            value,
+            weight: Interval {
+                min: min_weight,
+                max: max_weight,
+            },
+        });
+    }
+
+    let capacity = Interval {
+        min: solution.weight,
+        max: lightest_worth(problem, optimum + T::ONE, None)?.map(|weight| weight - T::ONE),
+    };
+    Ok(Sensitivity { items, capacity })
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::solver::test_util::Lcg;
+
+    fn example_problem(item_count: usize, seed: u64) -> Problem<u64> {
+        let items = Lcg::new(seed).items(item_count, 0..40, 1..26);
+        Problem::new(items, 5 * item_count as u64)
+    }
+
+    /// True if no decision beats the solution's
+    fn still_optimal(problem: &Problem<u64>, solution: &Solution<u64>) -> bool {
+        let value: u64 = (0..problem.items.len())
+            .map(|i| solution.decision[i] * problem.items[i].value)
+            .sum();
+        let weight: u64 = (0..problem.items.len())
+            .map(|i| solution.decision[i] * problem.items[i].weight)
+            .sum();
+        weight <= problem.capacity && value >= minknap::solve(problem).unwrap().value
+    }
+
+    #[test]
+    fn ranges_are_tight() {
+        for seed in 0..5 {
+            let problem = example_problem(12, seed);
+            let solution = minknap::solve(&problem).unwrap();
+            let sensitivity = analyse(&problem, &solution).unwrap();
+
+            for (index, item) in sensitivity.items.iter().enumerate() {
+                assert_eq!(item.taken, solution.decision[index] == 1);
+                let original = problem.items[index];
+                let mut changed = problem.clone();
+                for value in 0..=original.value + 60 {
+                    changed.items[index].value = value;
+                    let inside =
+                        value >= item.value.min && item.value.max.is_none_or(|m| value <= m);
+                    assert_eq!(
+                        inside,
+                        still_optimal(&changed, &solution),
+                        "seed {} item {} value {}",
+                        seed,
+                        index,
+                        value
+                    );
+                }
+                changed.items[index].value = original.value;
+                for weight in 0..=original.weight + 60 {
+                    changed.items[index].weight = weight;
+                    let inside =
+                        weight >= item.weight.min && item.weight.max.is_none_or(|m| weight <= m);
+                    assert_eq!(
+                        inside,
+                        still_optimal(&changed, &solution),
+                        "seed {} item {} weight {}",
+                        seed,
+                        index,
+                        weight
+                    );
+                }
+            }
+
+            let mut changed = problem.clone();
+            for capacity in 0..=problem.capacity + 60 {
+                changed.capacity = capacity;
+                let range = sensitivity.capacity;
+                let inside = capacity >= range.min && range.max.is_none_or(|m| capacity <= m);
+                assert_eq!(inside, still_optimal(&changed, &solution));
+            }
+        }
+    }
+
+    #[test]
+    fn greedy_solutions_are_rejected() {
+        let items = vec![
+            Item::new(0, 10u32, 6),
+            Item::new(1, 7, 5),
+            Item::new(2, 7, 5),
+        ];
+        let problem = Problem::new(items, 10);
+        let greedy = Solution::from_bools(&[true, false, false], 10, 6);
+        assert!(matches!(
+            analyse(&problem, &greedy),
+            Err(SolveError::InvalidProblem(_))
+        ));
+    }
+
+    #[test]
+    fn fixings_are_rejected() {
+        let mut problem = example_problem(5, 1);
+        let solution = minknap::solve(&problem).unwrap();
+        problem.fix(0, Fixing::Out);
+        assert!(matches!(
+            analyse(&problem, &solution),
+            Err(SolveError::InvalidProblem(_))
+        ));
+    }
+}
@@ -0,0 +1,230 @@
+// Subset-sum problem, the knapsack problem where every item is worth exactly its weight,
+// so the best solution is the largest sum of weights that fits in the capacity
+//
+// The sums reachable with the first i items are kept as a bitset over 0..=capacity.
+// Adding an item of weight w shifts the bitset up by w and ORs it into itself, which works
+// on 64 sums at a time.
+//
+// Backtracking needs the bitset from before each item, but keeping one per item would take
+// n * capacity bits. Instead a checkpoint is kept every sqrt(n) items, and the bitsets of
+// one block at a time are rebuilt from its checkpoint while backtracking, last block first.
+// This keeps about 2 * sqrt(n) bitsets, for twice the shifting work.
+
+use crate::solver::dynamic::MAX_BYTES;
+use crate::solver::integer::*;
+use crate::solver::problem::*;
+use crate::solver::registry::*;
+use std::mem::size_of;
+
+#[derive(Debug, Clone)]
+struct Bitset {
+    words: Vec<u64>,
+}
+
+impl Bitset {
+    /// Only the empty sum is reachable
+    fn new(bits: usize) -> Bitset {
+        let mut words = vec![0; bits.div_ceil(64)];
+        words[0] = 1;
+        Bitset { words }
+    }
+
+    fn get(&self, bit: usize) -> bool {
+        self.words[bit / 64] & (1 << (bit % 64)) != 0
+    }
+
+    /// Add `shift` to every reachable sum, keeping the old sums too.
+    /// Sums past the last word are dropped
+    fn shift_or(&mut self, shift: usize) {
+        let (word_shift, bit_shift) = (shift / 64, shift % 64);
+        // High words first, so every word read still holds its old value
+        for i in (word_shift..self.words.len()).rev() {
+            let source = i - word_shift;
+            let mut shifted = self.words[source] << bit_shift;
+            if bit_shift > 0 && source > 0 {
+                shifted |= self.words[source - 1] >> (64 - bit_shift);
+            }
+            self.words[i] |= shifted;
+        }
+    }
+
+    /// Largest reachable sum no greater than `limit`
+    fn highest_at_most(&self, limit: usize) -> usize {
+        let mut word_index = limit / 64;
+        // Ignore bits above the limit in its word
+        let mut word = self.words[word_index] & (u64::MAX >> (63 - limit % 64));
+        while word == 0 {
+            // The empty sum is always reachable, so this stops at word 0
+            word_index -= 1;
+            word = self.words[word_index];
+        }
+        word_index * 64 + 63 - word.leading_zeros() as usize
+    }
+}
+
+/// Word-parallel bitset dynamic program, for items that are worth their weight
+pub struct SubsetSum;
+
+impl<T: Integer> KnapsackSolver<T> for SubsetSum {
+    fn name(&self) -> &'static str {
+        "SubsetSum"
+    }
+
+    fn solve(
+        &self,
+        problem: &Problem<T>,
+        config: &SolveConfig,
+    ) -> Result<SolveReport<T>, SolveError> {
+        problem.check_supported("SubsetSum", &[Extension::Covering])?;
+        if problem.covering {
+            return solve_covering(self, problem, config);
+        }
+        if problem.fixings.is_some() {
+            return solve_fixed(self, problem, config);
+        }
+        problem.check_overflow()?;
+        if !problem.is_subset_sum() {
+            return Err(SolveError::InvalidProblem(
+                "the SubsetSum solver needs every item to be worth its weight".to_string(),
+            ));
+        }
+
+        let capacity = match problem.capacity.to_usize() {
+            Some(capacity) => capacity,
+            None => {
+                return Err(SolveError::TooLarge {
+                    size: usize::MAX,
+                    limit: MAX_BYTES * 8,
+                })
+            }
+        };
+        // Weightless items add nothing, and items over the capacity never fit, so
+        // every kept weight fits in a usize once the capacity does
+        let items: Vec<(usize, usize)> = problem
+            .items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| item.weight != T::ZERO && item.weight <= problem.capacity)
+            .map(|(index, item)| (index, item.weight.to_usize().unwrap()))
+            .collect();
+
+        let bits = capacity.saturating_add(1);
+        let bitset_bytes = bits.div_ceil(64).saturating_mul(size_of::<u64>());
+        let block = (items.len() as f64).sqrt().ceil().max(1.0) as usize;
+        let checkpoint_count = items.len().div_ceil(block).max(1);
+        let bytes = bitset_bytes.saturating_mul(checkpoint_count + block);
+        if bytes > MAX_BYTES {
+            return Err(SolveError::TooLarge {
+                size: bytes,
+                limit: MAX_BYTES,
+            });
+        }
+
+        // Checkpoints hold the bitset from before the first item of each block
+        let mut reachable = Bitset::new(bits);
+        let mut checkpoints = Vec::with_capacity(checkpoint_count);
+        let mut used = items.len();
+        for (i, &(_, weight)) in items.iter().enumerate() {
+            if i % block == 0 {
+                checkpoints.push(reachable.clone());
+            }
+            reachable.shift_or(weight);
+            // Nothing beats filling the capacity exactly
+            if reachable.get(capacity) {
+                used = i + 1;
+                break;
+            }
+        }
+        let best = reachable.highest_at_most(capacity);
+        if config.verbose {
+            println!(
+                "items: {}, items used: {}, block size: {}, best sum: {}",
+                items.len(),
+                used,
+                block,
+                best
+            );
+        }
+
+        let mut decision = vec![false; problem.items.len()];
+        let mut sum = best;
+        for (block_index, checkpoint) in checkpoints.iter().enumerate().rev() {
+            let start = block_index * block;
+            let end = used.min(start + block);
+            let mut before = vec![checkpoint.clone()];
+            for &(_, weight) in &items[start..end - 1] {
+                let mut next = before.last().unwrap().clone();
+                next.shift_or(weight);
+                before.push(next);
+            }
+
+            // `sum` is reachable with the items before `i + 1`,
+            // so if it is not reachable without item i, item i is taken
+            for i in (start..end).rev() {
+                if !before[i - start].get(sum) {
+                    let (index, weight) = items[i];
+                    decision[index] = true;
+                    sum -= weight;
+                }
+            }
+        }
+
+        let best = T::from_usize(best).unwrap();
+        let mut report = SolveReport::new(Solution::from_bools(&decision, best, best));
+        report.states_explored = Some(used.saturating_mul(bits));
+        report.bytes_used = Some(bytes);
+        Ok(report)
+    }
+}
+
+pub fn solve<T: Integer>(problem: &Problem<T>) -> Result<Solution<T>, SolveError> {
+    let report = SubsetSum.solve(problem, &SolveConfig::default())?;
+    Ok(report.solution)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::solver::dynamic;
+
+    fn subset_sum_problem(weights: &[u64], capacity: u64) -> Problem<u64> {
+        let items = weights
+            .iter()
+            .enumerate()
+            .map(|(id, &w)| Item::new(id, w, w))
+            .collect();
+        Problem::new(items, capacity)
+    }
+
+    #[test]
+    fn shift_crosses_words() {
+        let mut bitset = Bitset::new(300);
+        bitset.shift_or(70);
+        bitset.shift_or(63);
+        let set: Vec<usize> = (0..300).filter(|&b| bitset.get(b)).collect();
+        assert_eq!(set, vec![0, 63, 70, 133]);
+        assert_eq!(bitset.highest_at_most(132), 70);
+        assert_eq!(bitset.highest_at_most(62), 0);
+    }
+
+    #[test]
+    fn matches_dynamic_solver() {
+        let weights: Vec<u64> = (0..40).map(|i| (i * 37 + 11) % 97 + 40).collect();
+        for capacity in [0, 39, 50, 500, 1001, 2345, 10000] {
+            let problem = subset_sum_problem(&weights, capacity);
+            let solution = solve(&problem).unwrap();
+            assert!(solution.validate(&problem));
+            let expected = dynamic::solve(&problem).unwrap();
+            assert_eq!(solution.value, expected.value, "capacity {}", capacity);
+        }
+    }
+
+    #[test]
+    fn rejects_other_problems() {
+        let problem = Problem::new(vec![Item::new(0, 3u64, 2)], 5);
+        assert!(matches!(
+            solve(&problem),
+            Err(SolveError::InvalidProblem(_))
+        ));
+    }
+}
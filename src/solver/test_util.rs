@@ -0,0 +1,64 @@
+// Problem fixtures shared by the solver unit tests
+//
+// Problems are drawn from a linear congruential generator, so each seed always gives the
+// same problem and a failing seed can be rerun on its own.
+
+use crate::solver::problem::*;
+use std::ops::Range;
+
+pub(crate) struct Lcg {
+    state: u64,
+}
+
+impl Lcg {
+    pub(crate) fn new(seed: u64) -> Lcg {
+        Lcg { state: seed }
+    }
+
+    /// Draws a number below the bound
+    pub(crate) fn below(&mut self, bound: u64) -> u64 {
+        self.state = self
+            .state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.state >> 33) % bound
+    }
+
+    /// Draws a number in the range
+    pub(crate) fn within(&mut self, range: &Range<u64>) -> u64 {
+        range.start + self.below(range.end - range.start)
+    }
+
+    /// Items with values and weights drawn from the ranges, the value of each item first
+    pub(crate) fn items(
+        &mut self,
+        count: usize,
+        values: Range<u64>,
+        weights: Range<u64>,
+    ) -> Vec<Item<u64>> {
+        (0..count)
+            .map(|id| {
+                let value = self.within(&values);
+                let weight = self.within(&weights);
+                Item::new(id, value, weight)
+            })
+            .collect()
+    }
+}
+
+/// Every subset of the items, as which items it takes along with its value and weight
+pub(crate) fn subsets(problem: &Problem<u64>) -> impl Iterator<Item = (Vec<bool>, u64, u64)> + '_ {
+    let n = problem.items.len();
+    (0..1u32 << n).map(move |mask| {
+        let taken: Vec<bool> = (0..n).map(|i| mask & (1 << i) != 0).collect();
+        let (value, weight) = problem
+            .items
+            .iter()
+            .zip(&taken)
+            .filter(|(_, &taken)| taken)
+            .fold((0, 0), |(v, w), (item, _)| {
+                (v + item.value, w + item.weight)
+            });
+        (taken, value, weight)
+    })
+}
@@ -0,0 +1,326 @@
+// Unbounded knapsack problem, where every item can be taken any number of times
+//
+// Items dominated by multiples of another item are removed first. Item j is dominated
+// by item i when floor(w_j / w_i) copies of i weigh no more than j, and are worth at least as much.
+//
+// The remaining items are solved with a one dimensional dynamic program over capacities
+//   f(y) = max(f(y - 1), max_i f(y - w_i) + v_i)
+// which only needs to remember the last item added at each capacity to backtrack.
+//
+// Let b be the most efficient item. The optimal values are periodic past some capacity:
+// once f(y) = f(y - w_b) + v_b holds for w_max consecutive capacities, it holds for every
+// larger capacity too, since every value the recursion looks at is already periodic.
+// So the table is only filled until that happens, and larger capacities are brought back
+// into the table by taking copies of b. For large capacities this is usually far smaller
+// than a table covering the whole capacity.
+
+use crate::solver::dynamic::MAX_BYTES;
+use crate::solver::integer::*;
+use crate::solver::problem::*;
+use crate::solver::registry::*;
+use std::mem::size_of;
+
+/// Marks capacities whose best solution is the same as the capacity one below
+const NO_ITEM: u32 = u32::MAX;
+
+/// Dominance reduction and a periodicity aware dynamic program
+pub struct Unbounded;
+
+impl<T: Integer> KnapsackSolver<T> for Unbounded {
+    fn name(&self) -> &'static str {
+        "Unbounded"
+    }
+
+    fn solve(
+        &self,
+        problem: &Problem<T>,
+        config: &SolveConfig,
+    ) -> Result<SolveReport<T>, SolveError> {
+        problem.check_supported("Unbounded", &[Extension::Copies])?;
+        if problem.fixings.is_some() {
+            return solve_fixed(self, problem, config);
+        }
+        if !problem.is_unbounded() {
+            return Err(SolveError::InvalidProblem(
+                "the Unbounded solver needs unlimited copies of every item, see Problem::make_unbounded"
+                    .to_string(),
+            ));
+        }
+        // Also rejects weightless items with value, which make the profit unbounded
+        problem.check_overflow()?;
+
+        let items = undominated_items(problem);
+        let limit = MAX_BYTES / (size_of::<T>() + size_of::<u32>());
+        let mut table = Table::new(&items, problem.capacity, limit)?;
+        table.fill(&items)?;
+        if config.verbose {
+            println!(
+                "undominated items: {}, table size: {}, periodic: {}",
+                items.len(),
+                table.values.len(),
+                table.periodic
+            );
+        }
+
+        let solution = table.backtrack(problem, &items);
+        Ok(SolveReport {
+            solution,
+            states_explored: Some(table.values.len()),
+            bytes_used: Some(table.bytes_used()),
+        })
+    }
+}
+
+pub fn solve<T: Integer>(problem: &Problem<T>) -> Result<Solution<T>, SolveError> {
+    let report = Unbounded.solve(problem, &SolveConfig::default())?;
+    Ok(report.solution)
+}
+
+/// An item that survived dominance reduction
+struct UnboundedItem<T> {
+    index: usize,
+    value: T,
+    weight: T,
+}
+
+/// Items that fit, and are not dominated by multiples of another item,
+/// in order of efficiency so the most efficient item comes first
+fn undominated_items<T: Integer>(problem: &Problem<T>) -> Vec<UnboundedItem<T>> {
+    let mut order: Vec<usize> = (0..problem.items.len())
+        .filter(|&index| {
+            let item = &problem.items[index];
+            item.weight != T::ZERO && item.weight <= problem.capacity
+        })
+        .collect();
+
+    // Highest efficiency first, lightest first among equal efficiencies.
+    // A dominating item is always at least as efficient as the item it dominates,
+    // so it is always seen first
+    let items = &problem.items;
+    order.sort_unstable_by(|&a, &b| {
+        items[b]
+            .efficiency_cmp(&items[a])
+            .then(items[a].weight.cmp(&items[b].weight))
+    });
+
+    let mut kept: Vec<UnboundedItem<T>> = Vec::new();
+    for index in order {
+        let item = &items[index];
+        // Dominance is transitive, so checking kept items is enough
+        let dominated = kept
+            .iter()
+            .any(|k| k.weight <= item.weight && (item.weight / k.weight) * k.value >= item.value);
+        if !dominated {
+            kept.push(UnboundedItem {
+                index,
+                value: item.value,
+                weight: item.weight,
+            });
+        }
+    }
+    kept
+}
+
+struct Table<T> {
+    /// Best value at each capacity
+    values: Vec<T>,
+    /// Last item added for the best value at each capacity, or NO_ITEM
+    last_item: Vec<u32>,
+    /// Capacity the table needs to reach, unless it turns periodic first
+    target: usize,
+    /// Item weights as table offsets, in the same order as the items
+    weights: Vec<usize>,
+    /// Most capacities the table may hold
+    limit: usize,
+    periodic: bool,
+}
+
+impl<T: Integer> Table<T> {
+    fn new(items: &[UnboundedItem<T>], capacity: T, limit: usize) -> Result<Table<T>, SolveError> {
+        let target = capacity.to_usize().unwrap_or(usize::MAX);
+
+        // Every weight is at most the capacity, so only needs checking against the limit
+        let mut weights = Vec::with_capacity(items.len());
+        for item in items {
+            match item.weight.to_usize() {
+                Some(w) if w < limit => weights.push(w),
+                _ => {
+                    return Err(SolveError::TooLarge {
+                        size: item.weight.to_usize().unwrap_or(usize::MAX),
+                        limit,
+                    })
+                }
+            }
+        }
+        if items.len() >= NO_ITEM as usize {
+            return Err(SolveError::TooLarge {
+                size: items.len(),
+                limit: NO_ITEM as usize,
+            });
+        }
+
+        Ok(Table {
+            values: vec![T::ZERO],
+            last_item: vec![NO_ITEM],
+            target,
+            weights,
+            limit,
+            periodic: false,
+        })
+    }
+
+    fn bytes_used(&self) -> usize {
+        self.values.capacity() * size_of::<T>()
+            + self.last_item.capacity() * size_of::<u32>()
+            + self.weights.capacity() * size_of::<usize>()
+    }
+
+    fn fill(&mut self, items: &[UnboundedItem<T>]) -> Result<(), SolveError> {
+        let (best_weight, best_value) = match items.first() {
+            Some(best) => (self.weights[0], best.value),
+            None => return Ok(()),
+        };
+        let max_weight = *self.weights.iter().max().unwrap();
+
+        // Number of consecutive capacities where taking the best item was optimal
+        let mut periodic_run = 0;
+        let mut y = 0;
+        while y < self.target {
+            // The table may not turn periodic before running out of room
+            if self.values.len() >= self.limit {
+                return Err(SolveError::TooLarge {
+                    size: self.target.saturating_add(1),
+                    limit: self.limit,
+                });
+            }
+            y += 1;
+            let mut value = self.values[y - 1];
+            let mut last = NO_ITEM;
+            for (i, (item, &w)) in items.iter().zip(&self.weights).enumerate() {
+                if w <= y && self.values[y - w] + item.value > value {
+                    value = self.values[y - w] + item.value;
+                    last = i as u32;
+                }
+            }
+            self.values.push(value);
+            self.last_item.push(last);
+
+            if y >= best_weight && value == self.values[y - best_weight] + best_value {
+                periodic_run += 1;
+                if periodic_run >= max_weight {
+                    self.periodic = true;
+                    break;
+                }
+            } else {
+                periodic_run = 0;
+            }
+        }
+        Ok(())
+    }
+
+    fn backtrack(&self, problem: &Problem<T>, items: &[UnboundedItem<T>]) -> Solution<T> {
+        let mut decision = vec![T::ZERO; problem.items.len()];
+        let table_end = self.values.len() - 1;
+
+        // Past the end of the table, the answer repeats every copy of the best item
+        let mut y = table_end;
+        if let Some(best) = items.first() {
+            let table_end = T::from_usize(table_end).unwrap();
+            if problem.capacity > table_end {
+                let excess = problem.capacity - table_end;
+                let copies = (excess + best.weight - T::ONE) / best.weight;
+                decision[best.index] += copies;
+                let reduced = problem.capacity - copies * best.weight;
+                y = reduced.to_usize().unwrap();
+            }
+        }
+
+        while y > 0 {
+            match self.last_item[y] {
+                NO_ITEM => y -= 1,
+                i => {
+                    decision[items[i as usize].index] += T::ONE;
+                    y -= self.weights[i as usize];
+                }
+            }
+        }
+
+        let mut value = T::ZERO;
+        let mut weight = T::ZERO;
+        for (item, &count) in problem.items.iter().zip(&decision) {
+            value += item.value * count;
+            weight += item.weight * count;
+        }
+
+        Solution {
+            decision,
+            value,
+            weight,
+            bins: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::solver::bounded;
+
+    fn example_problem(capacity: u64) -> Problem<u64> {
+        let items = vec![
+            Item::new(0, 11u64, 7),
+            Item::new(1, 13, 9),
+            Item::new(2, 4, 3),
+            Item::new(3, 7, 6),
+            Item::new(4, 0, 0),
+        ];
+        let mut problem = Problem::new(items, capacity);
+        problem.make_unbounded();
+        problem
+    }
+
+    #[test]
+    fn dominated_items_removed() {
+        let problem = example_problem(100);
+        let kept: Vec<usize> = undominated_items(&problem)
+            .iter()
+            .map(|i| i.index)
+            .collect();
+        // 2 copies of item 2 beat item 3
+        assert_eq!(kept, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn matches_bounded_solver() {
+        for capacity in [0, 1, 5, 17, 40, 123, 1000] {
+            let problem = example_problem(capacity);
+            let solution = solve(&problem).unwrap();
+            assert!(solution.validate(&problem));
+            let expected = bounded::solve(&problem).unwrap();
+            assert_eq!(solution.value, expected.value, "capacity {}", capacity);
+        }
+    }
+
+    #[test]
+    fn periodic_table_stays_small() {
+        let problem = example_problem(1_000_000_000);
+        let report = Unbounded.solve(&problem, &SolveConfig::default()).unwrap();
+        assert!(report.solution.validate(&problem));
+        assert!(report.states_explored.unwrap() < 1000);
+    }
+
+    #[test]
+    fn table_that_stays_aperiodic_is_too_large() {
+        // Nearly equally efficient items only turn periodic after about a million capacities
+        let items = vec![Item::new(0, 1002u64, 1001), Item::new(1, 1000, 1000)];
+        let mut problem = Problem::new(items, 1_000_000_000);
+        problem.make_unbounded();
+        let items = undominated_items(&problem);
+        let mut table = Table::new(&items, problem.capacity, 10_000).unwrap();
+        assert!(matches!(
+            table.fill(&items),
+            Err(SolveError::TooLarge { limit: 10_000, .. })
+        ));
+        assert!(table.values.len() <= 10_000);
+    }
+}